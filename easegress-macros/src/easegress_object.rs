@@ -2,12 +2,80 @@
 
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
-use syn::{spanned::Spanned, Error, ImplItem, Item};
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Error, Expr, ExprLit, ExprUnary, FnArg, Ident, ImplItem, ImplItemMethod, Item, Lit, Token,
+    Type, UnOp,
+};
 
-pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
+/// Arguments of the attribute on the impl block, e.g. `#[easegress_object(init_failure = 1)]`.
+#[derive(Default)]
+struct Args {
+    /// The result `wasm_run` returns when the program failed to initialize.
+    init_failure: Option<Expr>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = Args::default();
+        let pairs = Punctuated::<Arg, Token![,]>::parse_terminated(input)?;
+        for arg in pairs {
+            match arg.name.to_string().as_str() {
+                "init_failure" => args.init_failure = Some(check_init_failure(arg.value)?),
+                _ => return Err(Error::new_spanned(arg.name, "Unknown argument")),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Check that a literal `init_failure` is one of the filter results `wasmResult1` to `wasmResult9`,
+/// other expressions are checked by their conversion into `i32` only.
+fn check_init_failure(value: Expr) -> syn::Result<Expr> {
+    let invalid = |value: &Expr| {
+        Error::new_spanned(
+            value,
+            "`init_failure` must be 1 to 9, which Easegress reports as `wasmResult1` to `wasmResult9`",
+        )
+    };
+    match &value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => match int.base10_parse::<i32>() {
+            Ok(1..=9) => Ok(value),
+            _ => Err(invalid(&value)),
+        },
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_), ..
+        }) => Err(invalid(&value)),
+        Expr::Path(path) if path.path.segments.last().unwrap().ident == "Continue" => {
+            Err(invalid(&value))
+        }
+        _ => Ok(value),
+    }
+}
+
+struct Arg {
+    name: Ident,
+    value: Expr,
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Arg { name, value })
+    }
+}
+
+pub fn expand_macro(attr: TokenStream, tokens: TokenStream) -> syn::Result<TokenStream> {
     let item = syn::parse2::<Item>(tokens)?;
     match item {
         Item::Impl(imp) => {
+            let args = syn::parse2::<Args>(attr)?;
             let impl_token = imp.impl_token;
             let trai = imp.trait_.clone();
             let (_, trai, _) =
@@ -24,8 +92,10 @@ pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
             let pound = syn::Token![#](imp.span()).to_token_stream();
 
             let struct_name = imp.self_ty;
+            let struct_str = struct_name.to_token_stream().to_string();
             let items = imp.items;
             let mut tokenized = vec![];
+            let mut constructor = None;
 
             for item in items {
                 let impl_method = match item {
@@ -39,7 +109,15 @@ pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
                 };
 
                 let tokens = match impl_method.sig.ident.to_string().as_str() {
-                    "new" => {
+                    "new" | "try_new" => {
+                        if constructor.is_some() {
+                            return Err(Error::new_spanned(
+                                &impl_method.sig.ident,
+                                "Only one of `new` and `try_new` can be implemented",
+                            ));
+                        }
+                        constructor =
+                            Some(expand_constructor(&struct_name, &struct_str, &impl_method));
                         let method = impl_method.clone();
                         quote! {
                             #method
@@ -56,6 +134,24 @@ pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
                 tokenized.push(tokens);
             }
 
+            let constructor = constructor.ok_or_else(|| {
+                Error::new_spanned(&struct_name, "Must implement `new` or `try_new`")
+            })?;
+
+            // Without a configured result, trap like before so Easegress reports `wasmError`. The
+            // panic message is lost in Wasm, so the reason is logged first.
+            let init_failure = match args.init_failure {
+                Some(result) => quote! { ::std::convert::Into::<i32>::into(#result) },
+                None => quote! {{
+                    let msg = format!(
+                        "{} is not initialized, check the log for the error, the request fails with wasmError",
+                        #struct_str
+                    );
+                    ::easegress_sdk::log(::easegress_sdk::LogLevel::Error, msg.clone());
+                    panic!("{}", msg)
+                }},
+            };
+
             Ok(quote! {
                 impl #struct_name {
                     #(#tokenized)*
@@ -63,41 +159,33 @@ pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
 
                 #pound[no_mangle]
                 pub extern "C" fn wasm_init(ptr: i32) {
-                    let mut params = HashMap::<String, String>::new();
-                    let p = ptr as *const i32;
-                    let length = unsafe { std::ptr::read(p) };
-                    let mut data:Vec<String> = Vec::with_capacity(length as usize);
-                    let mut offset = ptr + 4;
-                    if length > 0 {
-                        for i in 0..length {
-                            let len = unsafe { std::ptr::read(offset as *const i32)};
-                            offset += 4;
-                            let slice = unsafe {
-                                std::slice::from_raw_parts(offset as *const u8, (len - 1) as usize)
-                            };
-                            offset += len;
-                            data.push(String::from_utf8_lossy(slice).to_string());
-                        }
-                        for i in (0..data.len()).step_by(2) {
-                            params.insert(data.get(i).unwrap().to_owned(), data.get(i + 1).unwrap().to_owned());
-                        }
-                    }
+                    let params = ::easegress_sdk::unmarshal_params(ptr);
 
+                    let program = #constructor;
                     INIT.call_once(|| {
                         unsafe {
                             use std::borrow::BorrowMut;
-                            *PROGRAM.borrow_mut() = Some(std::sync::Mutex::new(#struct_name::new(params)));
+                            *PROGRAM.borrow_mut() = Some(std::sync::Mutex::new(program));
                         }
                     });
                 }
 
                 #pound[no_mangle]
                 pub extern "C" fn wasm_run() -> i32 {
-                    unsafe {PROGRAM.as_ref().unwrap().lock().unwrap().run()}
+                    match unsafe { PROGRAM.as_ref() } {
+                        Some(program) => ::std::convert::Into::<i32>::into(program.lock().unwrap().run()),
+                        None => #init_failure,
+                    }
                 }
             })
         }
         Item::Struct(struc) => {
+            if !attr.is_empty() {
+                return Err(Error::new_spanned(
+                    attr,
+                    "Arguments are only allowed on the impl of Program trait",
+                ));
+            }
             let tokens = struc.to_token_stream();
             let struct_name = struc.ident;
             Ok(quote!(
@@ -113,3 +201,109 @@ pub fn expand_macro(tokens: TokenStream) -> syn::Result<TokenStream> {
         )),
     }
 }
+
+/// Expand the expression creating the program in `wasm_init`, which logs the error and returns
+/// early if the parameters cannot be decoded or `try_new` fails.
+fn expand_constructor(
+    struct_name: &Type,
+    struct_str: &str,
+    method: &ImplItemMethod,
+) -> TokenStream {
+    let args = if takes_config(method) {
+        quote! {
+            match ::easegress_sdk::params::from_params(&params) {
+                Ok(config) => config,
+                Err(e) => {
+                    ::easegress_sdk::log(
+                        ::easegress_sdk::LogLevel::Error,
+                        format!("failed to decode parameters of {}: {}", #struct_str, e),
+                    );
+                    return;
+                }
+            }
+        }
+    } else {
+        quote! { params }
+    };
+
+    if method.sig.ident == "try_new" {
+        quote! {
+            match #struct_name::try_new(#args) {
+                Ok(program) => program,
+                Err(e) => {
+                    ::easegress_sdk::log(
+                        ::easegress_sdk::LogLevel::Error,
+                        format!("failed to initialize {}: {}", #struct_str, e),
+                    );
+                    return;
+                }
+            }
+        }
+    } else {
+        quote! { #struct_name::new(#args) }
+    }
+}
+
+/// Whether `new` takes a config struct to be decoded from the parameters rather than the
+/// `HashMap<String, String>` of the parameters itself.
+fn takes_config(method: &ImplItemMethod) -> bool {
+    let ty = match method.sig.inputs.first() {
+        Some(FnArg::Typed(arg)) => &*arg.ty,
+        _ => return false,
+    };
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|x| x.ident != "HashMap")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_impl(attr: TokenStream) -> syn::Result<TokenStream> {
+        let item = quote! {
+            impl Program for Fake {
+                fn new(_params: HashMap<String, String>) -> Self {
+                    Self
+                }
+
+                fn run(&self) -> i32 {
+                    0
+                }
+            }
+        };
+        expand_macro(attr, item)
+    }
+
+    #[test]
+    fn init_failure_in_range() {
+        assert!(expand_impl(quote! { init_failure = 1 }).is_ok());
+        assert!(expand_impl(quote! { init_failure = 9 }).is_ok());
+        assert!(expand_impl(quote! { init_failure = RunResult::Result3 }).is_ok());
+    }
+
+    #[test]
+    fn init_failure_out_of_range() {
+        for attr in [
+            quote! { init_failure = 0 },
+            quote! { init_failure = 10 },
+            quote! { init_failure = -1 },
+            quote! { init_failure = RunResult::Continue },
+        ] {
+            let err = expand_impl(attr).unwrap_err();
+            assert!(err.to_string().starts_with("`init_failure` must be 1 to 9"));
+        }
+    }
+
+    #[test]
+    fn unknown_argument() {
+        let err = expand_impl(quote! { on_failure = 1 }).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown argument");
+    }
+}
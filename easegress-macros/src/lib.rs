@@ -4,7 +4,7 @@
 //! #[easegress_object] can only be used on your struct and the implementation of the Program trait for your struct.
 //!
 //! # Examples
-//! ```
+//! ```ignore
 //! #[easegress_object]
 //! struct Fake;
 //!
@@ -20,8 +20,27 @@
 //! }
 //! ```
 //!
-//! # Errors
+//! `run` may also return `easegress_sdk::RunResult`, or any other type implementing `Into<i32>`.
+//!
+//! If initialization can fail, implement `try_new` returning a `Result` instead of `new`. The error
+//! is logged at `LogLevel::Error`, and `wasm_run` logs that the program is not initialized and
+//! traps, or returns the `init_failure` result when it is set on the impl block. `init_failure`
+//! must be 1 to 9, the filter results `wasmResult1` to `wasmResult9`.
+//!
+//! ```ignore
+//! #[easegress_object(init_failure = 1)]
+//! impl Program for Fake {
+//!     fn try_new(param: std::collections::HashMap<String, String>) -> Result<Self, String> {
+//!         if param.is_empty() {
+//!             return Err("parameters are required".to_string());
+//!         }
+//!         Ok(Self {})
+//!     }
+//! }
 //! ```
+//!
+//! # Errors
+//! ```compile_fail
 //! #[easegress_object]
 //! fn fake() {}
 //! ```
@@ -32,8 +51,8 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 
 #[proc_macro_attribute]
-pub fn easegress_object(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    easegress_object::expand_macro(item.into())
+pub fn easegress_object(attr: TokenStream, item: TokenStream) -> TokenStream {
+    easegress_object::expand_macro(attr.into(), item.into())
         .unwrap_or_else(syn::Error::into_compile_error)
         .into()
 }
@@ -0,0 +1,239 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Encode and decode bodies according to `Content-Encoding`, enabled by the `compression` feature.
+//!
+//! `gzip`, `deflate` and `br` are supported, all implemented in pure Rust so they work on wasm32.
+//! Decoding takes the maximum size of the decoded body, so a small compressed body can not expand
+//! beyond the memory of the filter.
+//!
+//! ```ignore
+//! const MAX_BODY_SIZE: usize = 4 << 20;
+//!
+//! let body = response::resp_get_decoded_body(MAX_BODY_SIZE)?;
+//! let body = String::from_utf8_lossy(&body).replace("http://", "https://");
+//! // compressed again with the Content-Encoding of the response
+//! response::resp_set_decoded_body(body.into_bytes())?;
+//! ```
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+
+/// `ContentEncoding` is a content coding, see RFC 9110 section 8.4.1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+    Deflate,
+    Brotli,
+}
+
+/// Error returned when a body can not be encoded or decoded.
+#[derive(Debug)]
+pub enum EncodingError {
+    /// The content coding is not supported, it holds the coding.
+    Unsupported(String),
+    /// Encoding or decoding failed, e.g. the body is corrupted.
+    Io(io::Error),
+    /// The decoded body is larger than the maximum size, it holds the maximum size.
+    TooLarge(usize),
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::Unsupported(coding) => {
+                write!(f, "unsupported content coding {:?}", coding)
+            }
+            EncodingError::Io(e) => write!(f, "failed to transcode body: {}", e),
+            EncodingError::TooLarge(max) => {
+                write!(f, "decoded body is larger than {} bytes", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodingError::Unsupported(_) | EncodingError::TooLarge(_) => None,
+            EncodingError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for EncodingError {
+    fn from(e: io::Error) -> Self {
+        EncodingError::Io(e)
+    }
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+
+    pub fn encode(&self, body: &[u8]) -> Result<Vec<u8>, EncodingError> {
+        let mut buf = Vec::new();
+        match self {
+            ContentEncoding::Identity => buf.extend_from_slice(body),
+            ContentEncoding::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(&mut buf, flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()?;
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(&mut buf, flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()?;
+            }
+            ContentEncoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(&mut buf, 4096, 5, 22);
+                encoder.write_all(body)?;
+                // the stream is finished when the encoder is consumed
+                encoder.into_inner();
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Decode `body`, failing with `EncodingError::TooLarge` if the result exceeds `max_size` bytes.
+    pub fn decode(&self, body: &[u8], max_size: usize) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            ContentEncoding::Identity => read_limited(body, max_size),
+            ContentEncoding::Gzip => {
+                read_limited(flate2::read::MultiGzDecoder::new(body), max_size)
+            }
+            // `deflate` is the zlib format, but some servers send raw deflate data.
+            ContentEncoding::Deflate => {
+                match read_limited(flate2::read::ZlibDecoder::new(body), max_size) {
+                    Err(EncodingError::Io(_)) => {
+                        read_limited(flate2::read::DeflateDecoder::new(body), max_size)
+                    }
+                    result => result,
+                }
+            }
+            ContentEncoding::Brotli => {
+                read_limited(brotli::Decompressor::new(body, 4096), max_size)
+            }
+        }
+    }
+}
+
+/// Read at most `max_size` bytes from `reader`, which must be exhausted by then.
+fn read_limited(reader: impl Read, max_size: usize) -> Result<Vec<u8>, EncodingError> {
+    let mut buf = Vec::new();
+    reader.take(max_size as u64 + 1).read_to_end(&mut buf)?;
+    if buf.len() > max_size {
+        return Err(EncodingError::TooLarge(max_size));
+    }
+    Ok(buf)
+}
+
+impl FromStr for ContentEncoding {
+    type Err = EncodingError;
+
+    /// Parse a content coding, which is case-insensitive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(ContentEncoding::Identity),
+            "gzip" | "x-gzip" => Ok(ContentEncoding::Gzip),
+            "deflate" => Ok(ContentEncoding::Deflate),
+            "br" => Ok(ContentEncoding::Brotli),
+            _ => Err(EncodingError::Unsupported(s.trim().to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ContentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse a Content-Encoding header value, which lists the codings in the order they were applied.
+pub fn parse_codings(value: &str) -> Result<Vec<ContentEncoding>, EncodingError> {
+    value
+        .split(',')
+        .map(str::parse)
+        .filter(|c| !matches!(c, Ok(ContentEncoding::Identity)))
+        .collect()
+}
+
+/// Decode `body` encoded with the codings of the Content-Encoding header value `encoding`, the
+/// result of each coding is limited to `max_size` bytes.
+pub fn decode(encoding: &str, body: &[u8], max_size: usize) -> Result<Vec<u8>, EncodingError> {
+    let mut body = body.to_vec();
+    for coding in parse_codings(encoding)?.iter().rev() {
+        body = coding.decode(&body, max_size)?;
+    }
+    Ok(body)
+}
+
+/// Encode `body` with the codings of the Content-Encoding header value `encoding`.
+pub fn encode(encoding: &str, body: &[u8]) -> Result<Vec<u8>, EncodingError> {
+    let mut body = body.to_vec();
+    for coding in parse_codings(encoding)?.iter() {
+        body = coding.encode(&body)?;
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODINGS: [ContentEncoding; 4] = [
+        ContentEncoding::Identity,
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+        ContentEncoding::Brotli,
+    ];
+
+    #[test]
+    fn round_trip() {
+        let body = b"hello, hello, hello".repeat(100);
+        for coding in CODINGS {
+            let encoded = coding.encode(&body).unwrap();
+            assert_eq!(coding.decode(&encoded, body.len()).unwrap(), body);
+        }
+        let encoded = encode("gzip, br", &body).unwrap();
+        assert_eq!(decode("gzip, br", &encoded, body.len()).unwrap(), body);
+    }
+
+    #[test]
+    fn limits_the_decoded_size() {
+        // 16MiB of zeros compress to a few KiB
+        let body = vec![0; 16 << 20];
+        for coding in CODINGS {
+            let encoded = coding.encode(&body).unwrap();
+            assert!(matches!(
+                coding.decode(&encoded, 1 << 20),
+                Err(EncodingError::TooLarge(max)) if max == 1 << 20
+            ));
+        }
+        let encoded = encode("gzip, gzip", &body).unwrap();
+        assert!(matches!(
+            decode("gzip, gzip", &encoded, 1 << 20),
+            Err(EncodingError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn decodes_raw_deflate() {
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"raw").unwrap();
+        let encoded = encoder.finish().unwrap();
+        assert_eq!(
+            ContentEncoding::Deflate.decode(&encoded, 3).unwrap(),
+            b"raw".to_vec()
+        );
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::time::{Duration, SystemTime};
+
+use super::{Cookie, Priority, SameSite};
+
+/// `CookieBuilder` builds a `Cookie`, it is created by `Cookie::build`.
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct CookieBuilder {
+    cookie: Cookie,
+}
+
+impl CookieBuilder {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            cookie: Cookie::new(name, value),
+        }
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.cookie.path = path.into();
+        self
+    }
+
+    /// Set the domain, which is stored in lower case without leading dots.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.cookie.set_domain(domain.into());
+        self
+    }
+
+    /// Set `Expires`, sub-second precision is dropped.
+    pub fn expires(mut self, expires: SystemTime) -> Self {
+        self.cookie.set_expires(Some(expires));
+        self
+    }
+
+    /// Set `Max-Age`, a zero `age` expires the cookie immediately. Sub-second precision is
+    /// dropped.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.cookie.set_max_age(Some(age));
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.cookie.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.cookie.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.cookie.same_site = same_site;
+        self
+    }
+
+    pub fn partitioned(mut self, partitioned: bool) -> Self {
+        self.cookie.partitioned = partitioned;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.cookie.priority = Some(priority);
+        self
+    }
+
+    pub fn finish(self) -> Cookie {
+        self.cookie
+    }
+}
+
+impl From<CookieBuilder> for Cookie {
+    fn from(builder: CookieBuilder) -> Self {
+        builder.finish()
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use super::Cookie;
+#[cfg(feature = "secure-cookies")]
+use super::{KeyRing, PrivateJar, SignedJar};
+
+/// `CookieJar` is a collection of cookies with distinct names, which tracks the changes made to
+/// it.
+///
+/// It is usually loaded with the cookies of the request by `from_request`. Cookies added or
+/// removed afterwards make up the delta, which `flush` sends as `Set-Cookie` headers of the
+/// response, a removed cookie is sent as an expired one.
+///
+/// With the `secure-cookies` feature, `signed` and `private` return views of the jar which sign
+/// or encrypt the cookies added through them, and verify or decrypt the cookies read through them.
+///
+/// ```ignore
+/// let mut jar = CookieJar::from_request();
+/// if jar.private(&keys).get("user").is_none() {
+///     jar.remove("session");
+/// }
+/// jar.flush();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Vec<Cookie>,
+    // names of the cookies the jar is loaded with
+    original: Vec<String>,
+    delta: Vec<Cookie>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return a jar holding the cookies of the request.
+    pub fn from_request() -> Self {
+        crate::request::get_all_cookie().into_iter().collect()
+    }
+
+    /// Add `c` without recording it in the delta, as if it was sent by the client.
+    ///
+    /// If the jar already has a cookie with the same name, `c` is ignored. A client sends the
+    /// cookie with the longest path first (RFC 6265 section 5.4), which is the one for the request.
+    pub fn add_original(&mut self, c: Cookie) {
+        if self.get(&c.name).is_some() {
+            return;
+        }
+        if !self.original.contains(&c.name) {
+            self.original.push(c.name.clone());
+        }
+        self.cookies.push(c);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        self.cookies.iter().find(|c| c.get_name() == name)
+    }
+
+    /// Add `c`, replacing the cookie with the same name.
+    pub fn add(&mut self, c: impl Into<Cookie>) {
+        let c = c.into();
+        self.record(c.clone());
+        self.replace(c);
+    }
+
+    /// Remove the cookie named `name` and return it.
+    ///
+    /// If the client sent the cookie, an expired cookie with the path and domain of the removed one
+    /// is recorded in the delta. As the client does not send them, use `remove_cookie` to remove a
+    /// cookie of the request which was set with a path or domain.
+    pub fn remove(&mut self, name: &str) -> Option<Cookie> {
+        let index = self.cookies.iter().position(|c| c.get_name() == name)?;
+        let c = self.cookies.remove(index);
+        self.record_removal(&c);
+        Some(c)
+    }
+
+    /// Remove the cookie with the name of `c`, the expired cookie in the delta has the path and
+    /// domain of `c`.
+    pub fn remove_cookie(&mut self, c: impl Into<Cookie>) -> Option<Cookie> {
+        let c = c.into();
+        let index = self.cookies.iter().position(|x| x.name == c.name);
+        self.record_removal(&c);
+        index.map(|index| self.cookies.remove(index))
+    }
+
+    /// Return the changes to send to the client, which are the added cookies and the expired
+    /// cookies for removals.
+    pub fn delta(&self) -> std::slice::Iter<'_, Cookie> {
+        self.delta.iter()
+    }
+
+    /// Send the delta as `Set-Cookie` headers of the response and clear it.
+    pub fn flush(&mut self) {
+        for c in self.delta.drain(..) {
+            crate::response::resp_set_cookie(c);
+        }
+        self.original = self.cookies.iter().map(|c| c.name.clone()).collect();
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Cookie> {
+        self.cookies.iter()
+    }
+
+    /// Return a view of the jar for cookies signed with `keys`.
+    #[cfg(feature = "secure-cookies")]
+    pub fn signed<'a>(&'a mut self, keys: &'a KeyRing) -> SignedJar<'a> {
+        SignedJar::new(self, keys)
+    }
+
+    /// Return a view of the jar for cookies encrypted with `keys`.
+    #[cfg(feature = "secure-cookies")]
+    pub fn private<'a>(&'a mut self, keys: &'a KeyRing) -> PrivateJar<'a> {
+        PrivateJar::new(self, keys)
+    }
+
+    fn replace(&mut self, c: Cookie) {
+        match self.cookies.iter_mut().find(|x| x.name == c.name) {
+            Some(x) => *x = c,
+            None => self.cookies.push(c),
+        }
+    }
+
+    fn record(&mut self, c: Cookie) {
+        match self.delta.iter_mut().find(|x| x.name == c.name) {
+            Some(x) => *x = c,
+            None => self.delta.push(c),
+        }
+    }
+
+    fn record_removal(&mut self, c: &Cookie) {
+        if !self.original.contains(&c.name) {
+            // the client does not have it, so there is nothing to expire
+            self.delta.retain(|x| x.name != c.name);
+            return;
+        }
+        self.record(Cookie {
+            name: c.name.clone(),
+            path: c.path.clone(),
+            domain: c.domain.clone(),
+            expires: Some(UNIX_EPOCH),
+            max_age: Some(Duration::ZERO),
+            ..Default::default()
+        });
+    }
+}
+
+/// Collect the cookies as originals, see `add_original`.
+impl FromIterator<Cookie> for CookieJar {
+    fn from_iter<T: IntoIterator<Item = Cookie>>(iter: T) -> Self {
+        let mut jar = Self::new();
+        for c in iter {
+            jar.add_original(c);
+        }
+        jar
+    }
+}
+
+impl IntoIterator for CookieJar {
+    type Item = Cookie;
+    type IntoIter = std::vec::IntoIter<Cookie>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cookies.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CookieJar {
+    type Item = &'a Cookie;
+    type IntoIter = std::slice::Iter<'a, Cookie>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cookies.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_first_cookie_of_a_name() {
+        let jar: CookieJar = Cookie::parse_header("id=specific; theme=dark; id=general")
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(jar.len(), 2);
+        assert_eq!(jar.get("id").unwrap().get_value(), "specific");
+        assert_eq!(jar.delta().count(), 0);
+    }
+
+    #[test]
+    fn tracks_changes() {
+        let mut jar: CookieJar = [Cookie::new("a", "1"), Cookie::new("b", "2")]
+            .into_iter()
+            .collect();
+        jar.add(Cookie::new("a", "3"));
+        jar.add(Cookie::new("c", "4"));
+        jar.remove("b");
+        jar.remove("c");
+
+        assert_eq!(jar.get("a").unwrap().get_value(), "3");
+        let delta: Vec<_> = jar.delta().map(Cookie::marshal).collect();
+        assert_eq!(
+            delta,
+            vec![
+                "a=3".to_string(),
+                "b=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0".to_string(),
+            ]
+        );
+    }
+}
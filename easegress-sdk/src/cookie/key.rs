@@ -0,0 +1,255 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::cell::RefCell;
+use std::fmt;
+use std::str::FromStr;
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// The minimum length of a secret a `Key` is derived from.
+pub const MIN_SECRET_LEN: usize = 32;
+
+/// `Key` holds the keys to sign and encrypt cookies, both derived from a secret.
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+/// Error returned when creating a `Key` or a `KeyRing`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /// The secret is shorter than `MIN_SECRET_LEN`, it holds the length.
+    TooShort(usize),
+    /// No secret is given.
+    Empty,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::TooShort(len) => write!(
+                f,
+                "cookie secret has {} bytes, at least {} are required",
+                len, MIN_SECRET_LEN
+            ),
+            KeyError::Empty => write!(f, "no cookie secret"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl Key {
+    /// Derive a key from `secret`, which must be random and at least `MIN_SECRET_LEN` bytes long.
+    pub fn derive(secret: &[u8]) -> Result<Self, KeyError> {
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(KeyError::TooShort(secret.len()));
+        }
+        let derive = |label: &[u8]| -> [u8; 32] {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key");
+            mac.update(label);
+            mac.finalize().into_bytes().into()
+        };
+        Ok(Self {
+            signing: derive(b"easegress cookie signing"),
+            encryption: derive(b"easegress cookie encryption"),
+        })
+    }
+
+    pub(super) fn signing(&self) -> &[u8; 32] {
+        &self.signing
+    }
+
+    pub(super) fn encryption(&self) -> &[u8; 32] {
+        &self.encryption
+    }
+}
+
+// the keys are never printed
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key { .. }")
+    }
+}
+
+/// `KeyRing` is a list of keys to support key rotation.
+///
+/// Cookies are always signed and encrypted with the first key, the primary one, but verified and
+/// decrypted with any of the keys. To rotate, put the new secret first and keep the old ones until
+/// the cookies issued with them have expired.
+///
+/// It can be parsed from a comma separated list of secrets, and deserialized from such a string or
+/// a sequence of secrets, so it can be a field of the config of `Program::new`:
+///
+/// ```ignore
+/// #[derive(Deserialize)]
+/// struct Config {
+///     // parameters:
+///     //   cookieSecrets: "new-secret-of-32-bytes-or-more...,old-secret-of-32-bytes-or-more..."
+///     #[serde(rename = "cookieSecrets")]
+///     keys: KeyRing,
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyRing {
+    keys: Vec<Key>,
+}
+
+impl KeyRing {
+    pub fn new(primary: Key) -> Self {
+        Self {
+            keys: vec![primary],
+        }
+    }
+
+    /// Parse a comma separated list of secrets, the first one is the primary.
+    pub fn parse(str: &str) -> Result<Self, KeyError> {
+        Self::from_secrets(str.split(',').map(str::trim).filter(|s| !s.is_empty()))
+    }
+
+    /// Derive the keys from `secrets`, the first one is the primary.
+    pub fn from_secrets<I, S>(secrets: I) -> Result<Self, KeyError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<[u8]>,
+    {
+        let keys = secrets
+            .into_iter()
+            .map(|s| Key::derive(s.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(KeyError::Empty);
+        }
+        Ok(Self { keys })
+    }
+
+    /// Add a fallback key, which is only used to verify and decrypt cookies.
+    pub fn push(&mut self, key: Key) {
+        self.keys.push(key);
+    }
+
+    pub fn primary(&self) -> &Key {
+        &self.keys[0]
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+}
+
+impl From<Key> for KeyRing {
+    fn from(key: Key) -> Self {
+        Self::new(key)
+    }
+}
+
+impl FromStr for KeyRing {
+    type Err = KeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyRing {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(KeyRingVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct KeyRingVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for KeyRingVisitor {
+    type Value = KeyRing;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a comma separated list of secrets, or a sequence of secrets")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<KeyRing, E> {
+        KeyRing::parse(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<KeyRing, A::Error> {
+        let mut secrets = Vec::new();
+        while let Some(secret) = seq.next_element::<String>()? {
+            secrets.push(secret);
+        }
+        KeyRing::from_secrets(secrets).map_err(serde::de::Error::custom)
+    }
+}
+
+thread_local! {
+    // the salt of the nonces of this instance, and the number of nonces issued
+    static NONCE_STATE: RefCell<Option<([u8; 16], u64)>> = const { RefCell::new(None) };
+}
+
+/// Return a nonce for XChaCha20-Poly1305.
+///
+/// The nonce is a per-instance salt followed by a counter, so an instance never repeats a nonce.
+/// There is no secure random source in the sandbox, so the salt is derived with HKDF from the time
+/// and `rand` of the host, it only has to differ between instances.
+pub(super) fn nonce() -> [u8; 24] {
+    NONCE_STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        let (salt, counter) = state.get_or_insert_with(|| (instance_salt(), 0));
+        *counter += 1;
+        let mut nonce = [0; 24];
+        nonce[..16].copy_from_slice(salt);
+        nonce[16..].copy_from_slice(&counter.to_le_bytes());
+        nonce
+    })
+}
+
+fn instance_salt() -> [u8; 16] {
+    let mut ikm = crate::get_unix_time_in_ms().to_le_bytes().to_vec();
+    for _ in 0..4 {
+        ikm.extend(crate::rand().to_bits().to_le_bytes());
+    }
+    let mut salt = [0; 16];
+    Hkdf::<Sha256>::new(None, &ikm)
+        .expand(b"easegress cookie nonce salt", &mut salt)
+        .expect("16 bytes can be expanded");
+    salt
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::{self, MockHost};
+
+    use super::*;
+
+    #[test]
+    fn nonces_are_salted_counters() {
+        host::set_host(MockHost::default());
+        let first = nonce();
+        let second = nonce();
+        assert_eq!(first[..16], second[..16]);
+        assert_eq!(first[16..], 1u64.to_le_bytes());
+        assert_eq!(second[16..], 2u64.to_le_bytes());
+
+        // another instance gets another salt
+        let salt = std::thread::spawn(|| {
+            let host = MockHost::default();
+            host.set_rand(0.5);
+            host::set_host(host);
+            nonce()[..16].to_vec()
+        })
+        .join()
+        .unwrap();
+        assert_ne!(salt, first[..16]);
+        host::reset_host();
+    }
+
+    #[test]
+    fn rejects_short_secrets() {
+        assert_eq!(Key::derive(b"short").unwrap_err(), KeyError::TooShort(5));
+        assert_eq!(KeyRing::parse(" , ").unwrap_err(), KeyError::Empty);
+    }
+}
@@ -0,0 +1,804 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::date::{fmt_http_date, parse_http_date};
+
+mod builder;
+mod jar;
+#[cfg(feature = "secure-cookies")]
+mod key;
+#[cfg(feature = "secure-cookies")]
+mod private;
+#[cfg(feature = "secure-cookies")]
+mod signed;
+
+pub use builder::CookieBuilder;
+pub use jar::CookieJar;
+#[cfg(feature = "secure-cookies")]
+pub use key::{Key, KeyError, KeyRing, MIN_SECRET_LEN};
+#[cfg(feature = "secure-cookies")]
+pub use private::PrivateJar;
+#[cfg(feature = "secure-cookies")]
+pub use signed::SignedJar;
+
+/// `Cookie` is an HTTP cookie, parsed from and serialized to `Set-Cookie` and `Cookie` headers
+/// as described in RFC 6265bis.
+///
+/// A `max_age` of zero expires the cookie immediately, a `Max-Age` of zero or less is parsed as
+/// zero, as user agents treat them the same.
+///
+/// ```ignore
+/// let c = Cookie::build("session", id)
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .http_only(true)
+///     .finish();
+/// response::resp_set_cookie(c);
+/// ```
+///
+/// Its string form is the `Set-Cookie` header value. With the `serde` feature, it is serialized as
+/// that string and deserialized with `Cookie::parse`, e.g. to store it in the cluster:
+///
+/// ```ignore
+/// cluster::put_string(key, serde_json::to_string(&c)?);
+/// ```
+///
+/// To make the string form lossless, the builder and the setters keep whole seconds of `expires`
+/// and `max_age` only, and store the domain in lower case without leading dots. Still, bytes which
+/// are not allowed in a `Set-Cookie` header are dropped, and a relative path or an `expires`
+/// outside of the years 1601 to 9999 is not parsed back.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: String,
+    domain: String,
+    expires: Option<SystemTime>,
+    max_age: Option<Duration>,
+    secure: bool,
+    http_only: bool,
+    same_site: SameSite,
+    partitioned: bool,
+    priority: Option<Priority>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub enum SameSite {
+    #[default]
+    DefaultMode = 0,
+    LaxMode = 1,
+    StrictMode = 2,
+    NoneMode = 3,
+}
+
+/// The `Priority` attribute, which is a Chrome extension.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// Error returned when parsing a cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CookieError {
+    /// Both the name and the value are empty.
+    Empty,
+    /// The name contains a control character.
+    InvalidName(String),
+    /// The value contains a control character.
+    InvalidValue(String),
+    /// The value of an attribute is unknown, it holds the value.
+    InvalidAttribute(String),
+}
+
+impl fmt::Display for CookieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookieError::Empty => write!(f, "cookie has neither name nor value"),
+            CookieError::InvalidName(name) => write!(f, "invalid cookie name {:?}", name),
+            CookieError::InvalidValue(value) => write!(f, "invalid cookie value {:?}", value),
+            CookieError::InvalidAttribute(value) => {
+                write!(f, "invalid cookie attribute value {:?}", value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CookieError {}
+
+// The maximum length of an attribute value, longer values are ignored, see RFC 6265bis
+// section 5.6.
+const MAX_ATTRIBUTE_VALUE_LEN: usize = 1024;
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Return a builder for a cookie with `name` and `value`.
+    pub fn build(name: impl Into<String>, value: impl Into<String>) -> CookieBuilder {
+        CookieBuilder::new(name, value)
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn get_value(&self) -> &str {
+        self.value.as_str()
+    }
+
+    pub fn set_value(&mut self, value: String) {
+        self.value = value;
+    }
+
+    pub fn get_path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    pub fn set_path(&mut self, path: String) {
+        self.path = path;
+    }
+
+    pub fn get_domain(&self) -> &str {
+        self.domain.as_str()
+    }
+
+    /// Set the domain, which is stored in lower case without leading dots.
+    pub fn set_domain(&mut self, domain: String) {
+        self.domain = normalize_domain(&domain);
+    }
+
+    pub fn get_expires(&self) -> Option<SystemTime> {
+        self.expires
+    }
+
+    /// Set `Expires`, sub-second precision is dropped.
+    pub fn set_expires(&mut self, expires: Option<SystemTime>) {
+        self.expires = expires.map(truncate_to_secs);
+    }
+
+    pub fn get_max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    /// Set `Max-Age`, sub-second precision is dropped.
+    pub fn set_max_age(&mut self, age: Option<Duration>) {
+        self.max_age = age.map(|age| Duration::from_secs(age.as_secs()));
+    }
+
+    pub fn get_secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn set_secure(&mut self, secure: bool) {
+        self.secure = secure;
+    }
+
+    pub fn get_http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn set_http_only(&mut self, val: bool) {
+        self.http_only = val;
+    }
+
+    pub fn get_same_site(&self) -> &SameSite {
+        self.same_site.borrow()
+    }
+
+    pub fn set_same_site(&mut self, val: SameSite) {
+        self.same_site = val;
+    }
+
+    pub fn get_partitioned(&self) -> bool {
+        self.partitioned
+    }
+
+    pub fn set_partitioned(&mut self, val: bool) {
+        self.partitioned = val;
+    }
+
+    pub fn get_priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn set_priority(&mut self, val: Option<Priority>) {
+        self.priority = val;
+    }
+
+    /// Serialize the cookie for a `Set-Cookie` header.
+    ///
+    /// Like Go, bytes not allowed in the value, path or domain are dropped, and a value
+    /// containing a space or a comma is quoted. A cookie without name is serialized as its value,
+    /// and a cookie whose name has no valid byte at all as an empty string, which the SDK does not
+    /// send.
+    pub fn marshal(&self) -> String {
+        let name = sanitize(&self.name, is_token);
+        if name.is_empty() && !self.name.is_empty() {
+            return String::new();
+        }
+        let value = sanitize(&self.value, is_cookie_octet_or_space);
+        let mut str = if name.is_empty() {
+            value
+        } else if value.contains([' ', ',']) {
+            format!("{}=\"{}\"", name, value)
+        } else {
+            format!("{}={}", name, value)
+        };
+
+        if !self.path.is_empty() {
+            str += format!("; Path={}", sanitize(&self.path, is_path_octet)).as_str();
+        }
+
+        if !self.domain.is_empty() {
+            let domain = sanitize(self.domain.trim_start_matches('.'), is_path_octet);
+            str += format!("; Domain={}", domain).as_str();
+        }
+
+        if let Some(expires) = self.expires {
+            str += format!("; Expires={}", fmt_http_date(expires)).as_str();
+        }
+
+        if let Some(age) = self.max_age {
+            str += format!("; Max-Age={}", age.as_secs()).as_str();
+        }
+
+        if self.secure {
+            str += "; Secure";
+        }
+
+        if self.http_only {
+            str += "; HttpOnly";
+        }
+
+        if self.same_site != SameSite::DefaultMode {
+            str += format!("; SameSite={}", self.same_site).as_str();
+        }
+
+        if self.partitioned {
+            str += "; Partitioned";
+        }
+
+        if let Some(priority) = self.priority {
+            str += format!("; Priority={}", priority).as_str();
+        }
+
+        str
+    }
+
+    /// Parse a `Set-Cookie` header value, see RFC 6265bis section 5.6.
+    ///
+    /// Unknown attributes and attributes with invalid values are ignored, like user agents do.
+    pub fn parse(str: &str) -> Result<Cookie, CookieError> {
+        let (pair, attributes) = str.split_once(';').unwrap_or((str, ""));
+        let mut c = parse_pair(pair)?;
+
+        for attribute in attributes.split(';') {
+            let (name, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            if value.len() > MAX_ATTRIBUTE_VALUE_LEN {
+                continue;
+            }
+
+            match name.trim().to_ascii_lowercase().as_str() {
+                // a path which is not absolute stands for the default path
+                "path" if value.starts_with('/') => c.path = value.to_string(),
+                "path" => c.path.clear(),
+                "domain" if !value.is_empty() => c.domain = normalize_domain(value),
+                "expires" => {
+                    if let Ok(expires) = parse_http_date(value) {
+                        c.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Some(age) = parse_max_age(value) {
+                        c.max_age = Some(age);
+                    }
+                }
+                "secure" => c.secure = true,
+                "httponly" => c.http_only = true,
+                "samesite" => c.same_site = value.parse().unwrap_or_default(),
+                "partitioned" => c.partitioned = true,
+                "priority" => {
+                    if let Ok(priority) = value.parse() {
+                        c.priority = Some(priority);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(c)
+    }
+
+    /// Parse the cookies of a `Cookie` header value, like `a=1; b=2`, see RFC 6265bis section 4.2.
+    pub fn parse_header(str: &str) -> impl Iterator<Item = Result<Cookie, CookieError>> + '_ {
+        str.split(';')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(parse_pair)
+    }
+
+    /// Parse a `Set-Cookie` header value, returning `None` if it is invalid. See `parse`.
+    pub fn unmarshal(str: String) -> Option<Cookie> {
+        Cookie::parse(&str).ok()
+    }
+
+    /// Like `marshal`, but log a cookie serialized as an empty string, for the setters sending it.
+    pub(crate) fn marshal_or_log(&self) -> Option<String> {
+        let str = self.marshal();
+        if str.is_empty() {
+            crate::log(
+                crate::LogLevel::Error,
+                format!("cookie is dropped: invalid name {:?}", self.name),
+            );
+            return None;
+        }
+        Some(str)
+    }
+}
+
+impl FromStr for Cookie {
+    type Err = CookieError;
+
+    /// Parse a `Set-Cookie` header value, see `Cookie::parse`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Cookie::parse(s)
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.marshal())
+    }
+}
+
+impl From<Cookie> for String {
+    fn from(c: Cookie) -> Self {
+        c.marshal()
+    }
+}
+
+impl TryFrom<String> for Cookie {
+    type Error = CookieError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Cookie::parse(&s)
+    }
+}
+
+impl SameSite {
+    /// Return the attribute value, which is empty for `DefaultMode`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::DefaultMode => "",
+            SameSite::LaxMode => "Lax",
+            SameSite::StrictMode => "Strict",
+            SameSite::NoneMode => "None",
+        }
+    }
+}
+
+impl FromStr for SameSite {
+    type Err = CookieError;
+
+    /// Parse an attribute value case-insensitively, an empty value is `DefaultMode`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" => Ok(SameSite::DefaultMode),
+            "lax" => Ok(SameSite::LaxMode),
+            "strict" => Ok(SameSite::StrictMode),
+            "none" => Ok(SameSite::NoneMode),
+            _ => Err(CookieError::InvalidAttribute(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<SameSite> for String {
+    fn from(s: SameSite) -> Self {
+        s.as_str().to_string()
+    }
+}
+
+impl TryFrom<String> for SameSite {
+    type Error = CookieError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = CookieError;
+
+    /// Parse an attribute value case-insensitively.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            _ => Err(CookieError::InvalidAttribute(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<Priority> for String {
+    fn from(p: Priority) -> Self {
+        p.as_str().to_string()
+    }
+}
+
+impl TryFrom<String> for Priority {
+    type Error = CookieError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl Default for Cookie {
+    fn default() -> Self {
+        Self {
+            name: "".to_string(),
+            value: "".to_string(),
+            path: "".to_string(),
+            domain: "".to_string(),
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: SameSite::default(),
+            partitioned: false,
+            priority: None,
+        }
+    }
+}
+
+/// Parse a cookie name-value pair, the value is unquoted.
+fn parse_pair(pair: &str) -> Result<Cookie, CookieError> {
+    // a pair without '=' is a cookie with an empty name
+    let (name, value) = match pair.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => ("", pair.trim()),
+    };
+    if name.is_empty() && value.is_empty() {
+        return Err(CookieError::Empty);
+    }
+    if name.bytes().any(is_ctl) {
+        return Err(CookieError::InvalidName(name.to_string()));
+    }
+    if value.bytes().any(is_ctl) {
+        return Err(CookieError::InvalidValue(value.to_string()));
+    }
+
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(unquoted) => unquoted,
+        None => value,
+    };
+    Ok(Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        ..Default::default()
+    })
+}
+
+/// Parse a Max-Age value, which is an optional `-` followed by digits. Negative values are
+/// parsed as zero and values which are too large are saturated.
+fn parse_max_age(value: &str) -> Option<Duration> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    if negative {
+        return Some(Duration::ZERO);
+    }
+    Some(Duration::from_secs(digits.parse().unwrap_or(u64::MAX)))
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim_start_matches('.').to_ascii_lowercase()
+}
+
+/// Drop the sub-second part of `time`, rounding down like `fmt_http_date`.
+fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => UNIX_EPOCH + Duration::from_secs(d.as_secs()),
+        Err(e) => {
+            let d = e.duration();
+            let secs = d.as_secs() + u64::from(d.subsec_nanos() > 0);
+            UNIX_EPOCH - Duration::from_secs(secs)
+        }
+    }
+}
+
+fn sanitize(str: &str, valid: fn(u8) -> bool) -> String {
+    if str.bytes().all(valid) {
+        return str.to_string();
+    }
+    String::from_utf8_lossy(&str.bytes().filter(|b| valid(*b)).collect::<Vec<_>>()).to_string()
+}
+
+/// CTLs, see RFC 6265bis section 5.6, horizontal tab is allowed.
+fn is_ctl(b: u8) -> bool {
+    (b <= 0x1f && b != b'\t') || b == 0x7f
+}
+
+fn is_token(b: u8) -> bool {
+    crate::header::is_tchar(b)
+}
+
+/// cookie-octet, see RFC 6265 section 4.1.1, plus space and comma which are allowed in quoted
+/// values.
+fn is_cookie_octet_or_space(b: u8) -> bool {
+    (0x20..0x7f).contains(&b) && b != b'"' && b != b';' && b != b'\\'
+}
+
+/// av-octet, any CHAR except CTLs or `;`.
+fn is_path_octet(b: u8) -> bool {
+    (0x20..0x7f).contains(&b) && b != b';'
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn round_trip(c: &Cookie) -> Cookie {
+        Cookie::parse(&c.marshal()).unwrap()
+    }
+
+    fn time() -> impl Strategy<Value = SystemTime> {
+        // from 1601-01-01 to 9999-12-31, the years an HTTP date is parsed in
+        (-11644473600i64..253402300800, 0u32..1_000_000_000).prop_map(|(secs, nanos)| {
+            let time = if secs >= 0 {
+                UNIX_EPOCH + Duration::from_secs(secs as u64)
+            } else {
+                UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+            };
+            time + Duration::from_nanos(nanos.into())
+        })
+    }
+
+    fn same_site() -> impl Strategy<Value = SameSite> {
+        prop_oneof![
+            Just(SameSite::DefaultMode),
+            Just(SameSite::LaxMode),
+            Just(SameSite::StrictMode),
+            Just(SameSite::NoneMode),
+        ]
+    }
+
+    fn priority() -> impl Strategy<Value = Option<Priority>> {
+        prop_oneof![
+            Just(None),
+            Just(Some(Priority::Low)),
+            Just(Some(Priority::Medium)),
+            Just(Some(Priority::High)),
+        ]
+    }
+
+    prop_compose! {
+        fn built_cookie()(
+            name in "[A-Za-z0-9!#$%&'*+.^_`|~-]{1,12}",
+            value in r"([!#-:<-\[\]-~]([ !#-:<-\[\]-~]{0,16}[!#-:<-\[\]-~])?)?",
+            path in "(/[A-Za-z0-9._~%-]{0,8}){0,3}",
+            domain in r"\.{0,2}[A-Za-z0-9-]{1,8}(\.[A-Za-z0-9-]{1,8}){0,2}",
+            expires in prop::option::of(time()),
+            max_age in prop::option::of(any::<Duration>()),
+            flags in any::<(bool, bool, bool)>(),
+            same_site in same_site(),
+            priority in priority(),
+        ) -> Cookie {
+            let mut b = Cookie::build(name, value)
+                .path(path)
+                .domain(domain)
+                .secure(flags.0)
+                .http_only(flags.1)
+                .same_site(same_site)
+                .partitioned(flags.2);
+            if let Some(expires) = expires {
+                b = b.expires(expires);
+            }
+            if let Some(age) = max_age {
+                b = b.max_age(age);
+            }
+            if let Some(priority) = priority {
+                b = b.priority(priority);
+            }
+            b.finish()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn round_trips_built_cookies(c in built_cookie()) {
+            prop_assert_eq!(c.to_string().parse::<Cookie>(), Ok(c));
+        }
+    }
+
+    #[test]
+    fn normalizes_when_built() {
+        let c = Cookie::build("a", "b")
+            .domain("..Example.COM")
+            .expires(UNIX_EPOCH + Duration::from_millis(1500))
+            .max_age(Duration::from_millis(2500))
+            .finish();
+        assert_eq!(c.get_domain(), "example.com");
+        assert_eq!(c.get_expires(), Some(UNIX_EPOCH + Duration::from_secs(1)));
+        assert_eq!(c.get_max_age(), Some(Duration::from_secs(2)));
+
+        let mut c = c;
+        c.set_expires(Some(UNIX_EPOCH - Duration::from_millis(500)));
+        assert_eq!(c.get_expires(), Some(UNIX_EPOCH - Duration::from_secs(1)));
+        c.set_expires(Some(UNIX_EPOCH - Duration::from_secs(2)));
+        assert_eq!(c.get_expires(), Some(UNIX_EPOCH - Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn round_trips_attributes() {
+        let c = Cookie::build("session", "abc")
+            .path("/app")
+            .domain("example.com")
+            .expires(UNIX_EPOCH + Duration::from_secs(784111777))
+            .max_age(Duration::from_secs(3600))
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::StrictMode)
+            .partitioned(true)
+            .priority(Priority::High)
+            .finish();
+        assert_eq!(
+            c.marshal(),
+            "session=abc; Path=/app; Domain=example.com; Expires=Sun, 06 Nov 1994 08:49:37 GMT; \
+             Max-Age=3600; Secure; HttpOnly; SameSite=Strict; Partitioned; Priority=High"
+        );
+        assert_eq!(round_trip(&c), c);
+    }
+
+    #[test]
+    fn round_trips_quoted_values() {
+        let c = Cookie::new("a", "b c,d");
+        assert_eq!(c.marshal(), "a=\"b c,d\"");
+        assert_eq!(round_trip(&c), c);
+
+        let c = Cookie::parse("a=\"quoted\"; Path=/").unwrap();
+        assert_eq!(c.get_value(), "quoted");
+        assert_eq!(round_trip(&c), c);
+    }
+
+    #[test]
+    fn parses_non_positive_max_age_as_zero() {
+        for max_age in ["0", "-1", "-9999999999999999999999"] {
+            let c = Cookie::parse(&format!("a=b; Max-Age={}", max_age)).unwrap();
+            assert_eq!(c.get_max_age(), Some(Duration::ZERO));
+            assert_eq!(c.marshal(), "a=b; Max-Age=0");
+            assert_eq!(round_trip(&c), c);
+        }
+        let c = Cookie::parse("a=b; Max-Age=1x").unwrap();
+        assert_eq!(c.get_max_age(), None);
+    }
+
+    #[test]
+    fn round_trips_nameless_cookies() {
+        let c = Cookie::parse("value; Secure").unwrap();
+        assert_eq!(c.get_name(), "");
+        assert_eq!(c.get_value(), "value");
+        assert_eq!(c.marshal(), "value; Secure");
+        assert_eq!(round_trip(&c), c);
+
+        let c = Cookie::parse("=value").unwrap();
+        assert_eq!(c.get_name(), "");
+        assert_eq!(round_trip(&c), c);
+        assert_eq!(Cookie::parse("=; Secure"), Err(CookieError::Empty));
+    }
+
+    #[test]
+    fn resets_relative_paths() {
+        let c = Cookie::parse("a=b; Path=/app; Path=app").unwrap();
+        assert_eq!(c.get_path(), "");
+        let c = Cookie::parse("a=b; Path=app; Path=/app").unwrap();
+        assert_eq!(c.get_path(), "/app");
+        let c = Cookie::parse("a=b; Path=").unwrap();
+        assert_eq!(c.get_path(), "");
+    }
+
+    #[test]
+    fn does_not_marshal_invalid_names() {
+        assert_eq!(Cookie::new("a b", "c").marshal(), "ab=c");
+        assert_eq!(Cookie::new("()", "c").marshal(), "");
+        assert_eq!(Cookie::new("", "").marshal(), "");
+
+        let host = crate::host::MockHost::default();
+        crate::host::set_host(host.clone());
+        crate::response::resp_set_cookie(Cookie::new("()", "c"));
+        assert_eq!(host.response().get_header("Set-Cookie"), None);
+        assert_eq!(host.logs().len(), 1);
+        crate::host::reset_host();
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn serializes_as_string() {
+        let c = Cookie::build("session", "abc")
+            .path("/app")
+            .same_site(SameSite::LaxMode)
+            .priority(Priority::High)
+            .finish();
+        let json = serde_json::to_string(&c).unwrap();
+        assert_eq!(
+            json,
+            r#""session=abc; Path=/app; SameSite=Lax; Priority=High""#
+        );
+        assert_eq!(serde_json::from_str::<Cookie>(&json).unwrap(), c);
+        assert!(serde_json::from_str::<Cookie>("{}").is_err());
+        assert!(serde_json::from_str::<Cookie>(r#""""#).is_err());
+
+        assert_eq!(
+            serde_json::to_string(&SameSite::NoneMode).unwrap(),
+            r#""None""#
+        );
+        assert_eq!(
+            serde_json::from_str::<SameSite>(r#""strict""#).unwrap(),
+            SameSite::StrictMode
+        );
+        assert!(serde_json::from_str::<SameSite>(r#""NoneMode""#).is_err());
+        assert_eq!(
+            serde_json::from_str::<Priority>(r#""Low""#).unwrap(),
+            Priority::Low
+        );
+    }
+}
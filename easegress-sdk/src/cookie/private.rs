@@ -0,0 +1,145 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, Payload};
+use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce};
+
+use super::key::nonce;
+use super::{Cookie, CookieJar, KeyRing};
+
+const NONCE_LEN: usize = 24;
+
+impl KeyRing {
+    /// Encrypt the value of `c` with the primary key.
+    ///
+    /// The value is encrypted with XChaCha20-Poly1305, authenticating the name as associated data,
+    /// and replaced by the base64 encoded nonce and ciphertext.
+    pub fn encrypt(&self, c: &mut Cookie) {
+        let cipher = XChaCha20Poly1305::new(self.primary().encryption().into());
+        let nonce = nonce();
+        let payload = Payload {
+            msg: c.value.as_bytes(),
+            aad: c.name.as_bytes(),
+        };
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), payload)
+            .expect("the value is short enough to encrypt");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        c.value = URL_SAFE_NO_PAD.encode(sealed);
+    }
+
+    /// Decrypt `c` with any of the keys, and return it with the original value.
+    pub fn decrypt(&self, c: &Cookie) -> Option<Cookie> {
+        let sealed = URL_SAFE_NO_PAD.decode(&c.value).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let value = self.keys().iter().find_map(|key| {
+            let cipher = XChaCha20Poly1305::new(key.encryption().into());
+            let payload = Payload {
+                msg: ciphertext,
+                aad: c.name.as_bytes(),
+            };
+            cipher.decrypt(XNonce::from_slice(nonce), payload).ok()
+        })?;
+
+        let mut decrypted = c.clone();
+        decrypted.value = String::from_utf8(value).ok()?;
+        Some(decrypted)
+    }
+}
+
+/// `PrivateJar` is a view of a `CookieJar` whose cookies are encrypted, see `KeyRing::encrypt`.
+pub struct PrivateJar<'a> {
+    jar: &'a mut CookieJar,
+    keys: &'a KeyRing,
+}
+
+impl<'a> PrivateJar<'a> {
+    pub(super) fn new(jar: &'a mut CookieJar, keys: &'a KeyRing) -> Self {
+        Self { jar, keys }
+    }
+
+    /// Return the cookie named `name` decrypted, if it is authentic.
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        self.keys.decrypt(self.jar.get(name)?)
+    }
+
+    /// Encrypt `c` and add it to the jar.
+    pub fn add(&mut self, c: impl Into<Cookie>) {
+        let mut c = c.into();
+        self.keys.encrypt(&mut c);
+        self.jar.add(c);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Cookie> {
+        self.jar.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::{self, MockHost};
+
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+    const OLD_SECRET: &str = "fedcba9876543210fedcba9876543210";
+
+    fn encrypted(keys: &KeyRing, name: &str, value: &str) -> Cookie {
+        let mut c = Cookie::new(name, value);
+        keys.encrypt(&mut c);
+        c
+    }
+
+    #[test]
+    fn round_trip() {
+        host::set_host(MockHost::default());
+        let keys = KeyRing::parse(SECRET).unwrap();
+        let c = encrypted(&keys, "user", "alice");
+        assert!(!c.get_value().contains("alice"));
+        assert_eq!(keys.decrypt(&c).unwrap().get_value(), "alice");
+
+        // the nonce differs, so the same value is encrypted differently
+        assert_ne!(encrypted(&keys, "user", "alice").get_value(), c.get_value());
+
+        // cookies of a rotated key are still decrypted
+        let old = KeyRing::parse(OLD_SECRET).unwrap();
+        let c = encrypted(&old, "user", "bob");
+        let rotated = KeyRing::parse(&format!("{},{}", SECRET, OLD_SECRET)).unwrap();
+        assert_eq!(rotated.decrypt(&c).unwrap().get_value(), "bob");
+        assert!(keys.decrypt(&c).is_none());
+        host::reset_host();
+    }
+
+    #[test]
+    fn rejects_tampered_cookies() {
+        host::set_host(MockHost::default());
+        let keys = KeyRing::parse(SECRET).unwrap();
+        let c = encrypted(&keys, "user", "alice");
+
+        let mut tampered = c.clone();
+        let mut value = c.get_value().to_string().into_bytes();
+        let last = value.len() - 1;
+        value[last] = if value[last] == b'A' { b'B' } else { b'A' };
+        tampered.set_value(String::from_utf8(value).unwrap());
+        assert!(keys.decrypt(&tampered).is_none());
+
+        // the name is authenticated, so the value can not be moved to another cookie
+        let mut renamed = c.clone();
+        renamed.set_name("admin".to_string());
+        assert!(keys.decrypt(&renamed).is_none());
+
+        let mut truncated = c.clone();
+        truncated.set_value(c.get_value()[..20].to_string());
+        assert!(keys.decrypt(&truncated).is_none());
+
+        let mut plain = c;
+        plain.set_value("alice".to_string());
+        assert!(keys.decrypt(&plain).is_none());
+        host::reset_host();
+    }
+}
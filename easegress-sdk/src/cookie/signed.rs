@@ -0,0 +1,108 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::{Cookie, CookieJar, Key, KeyRing};
+
+// the length of a base64 encoded HMAC-SHA256 tag
+const TAG_LEN: usize = 43;
+
+impl KeyRing {
+    /// Sign `c` with the primary key, the tag is prepended to its value.
+    ///
+    /// The tag is an HMAC-SHA256 over the name and the value, so a signed value can not be moved
+    /// to another cookie.
+    pub fn sign(&self, c: &mut Cookie) {
+        let tag = mac(self.primary(), c).finalize().into_bytes();
+        c.value = URL_SAFE_NO_PAD.encode(tag) + &c.value;
+    }
+
+    /// Verify the signature of `c` with any of the keys, and return it with the original value.
+    pub fn verify(&self, c: &Cookie) -> Option<Cookie> {
+        let tag = URL_SAFE_NO_PAD.decode(c.value.get(..TAG_LEN)?).ok()?;
+        let mut verified = c.clone();
+        verified.value = c.value[TAG_LEN..].to_string();
+        self.keys()
+            .iter()
+            .any(|key| mac(key, &verified).verify_slice(&tag).is_ok())
+            .then_some(verified)
+    }
+}
+
+fn mac(key: &Key, c: &Cookie) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.signing()).expect("HMAC accepts any key");
+    mac.update(c.name.as_bytes());
+    mac.update(b"=");
+    mac.update(c.value.as_bytes());
+    mac
+}
+
+/// `SignedJar` is a view of a `CookieJar` whose cookies are signed, see `KeyRing::sign`.
+pub struct SignedJar<'a> {
+    jar: &'a mut CookieJar,
+    keys: &'a KeyRing,
+}
+
+impl<'a> SignedJar<'a> {
+    pub(super) fn new(jar: &'a mut CookieJar, keys: &'a KeyRing) -> Self {
+        Self { jar, keys }
+    }
+
+    /// Return the cookie named `name` if its signature is valid.
+    pub fn get(&self, name: &str) -> Option<Cookie> {
+        self.keys.verify(self.jar.get(name)?)
+    }
+
+    /// Sign `c` and add it to the jar.
+    pub fn add(&mut self, c: impl Into<Cookie>) {
+        let mut c = c.into();
+        self.keys.sign(&mut c);
+        self.jar.add(c);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Cookie> {
+        self.jar.remove(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    #[test]
+    fn round_trip() {
+        let keys = KeyRing::parse(SECRET).unwrap();
+        let mut c = Cookie::new("user", "alice");
+        keys.sign(&mut c);
+        assert_eq!(c.get_value().len(), TAG_LEN + "alice".len());
+        assert!(c.get_value().ends_with("alice"));
+        assert_eq!(keys.verify(&c).unwrap().get_value(), "alice");
+    }
+
+    #[test]
+    fn rejects_tampered_cookies() {
+        let keys = KeyRing::parse(SECRET).unwrap();
+        let mut c = Cookie::new("user", "alice");
+        keys.sign(&mut c);
+
+        let mut tampered = c.clone();
+        tampered.set_value(c.get_value().replace("alice", "admin"));
+        assert!(keys.verify(&tampered).is_none());
+
+        let mut renamed = c.clone();
+        renamed.set_name("admin".to_string());
+        assert!(keys.verify(&renamed).is_none());
+
+        let mut short = c.clone();
+        short.set_value(c.get_value()[..TAG_LEN - 1].to_string());
+        assert!(keys.verify(&short).is_none());
+
+        let other = KeyRing::parse(&SECRET.replace('0', "1")).unwrap();
+        assert!(other.verify(&c).is_none());
+    }
+}
@@ -0,0 +1,293 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Format and parse HTTP dates, like `Sun, 06 Nov 1994 08:49:37 GMT`.
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Error returned when a date can not be parsed, it holds the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDate(pub String);
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid date {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDate {}
+
+/// Format `time` as an IMF-fixdate, see RFC 9110 section 5.6.7. Sub-second precision is dropped.
+pub fn fmt_http_date(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    let days = secs.div_euclid(86400);
+    let secs = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Parse an HTTP date.
+///
+/// Besides IMF-fixdate, the obsolete RFC 850 and asctime formats and the other variants found in
+/// `Expires` attributes are accepted, using the algorithm of RFC 6265 section 5.1.1.
+pub fn parse_http_date(str: &str) -> Result<SystemTime, InvalidDate> {
+    let invalid = || InvalidDate(str.to_string());
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in str.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            if let Some(t) = parse_time(token) {
+                time = Some(t);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = leading_digits(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(m) = parse_month(token) {
+                month = Some(m);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let ((hour, minute, second), day, month, mut year) = match (time, day, month, year) {
+        (Some(t), Some(d), Some(m), Some(y)) => (t, d, m, y),
+        _ => return Err(invalid()),
+    };
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    if year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+    if day < 1 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    let secs = days_from_civil(year as i64, month, day) * 86400
+        + (hour * 3600 + minute * 60 + second) as i64;
+    if secs >= 0 {
+        Ok(UNIX_EPOCH + Duration::from_secs(secs as u64))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+            .ok_or_else(invalid)
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~')
+}
+
+/// Parse `min` to `max` digits which may be followed by a non-digit and anything else.
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<u32> {
+    let len = token.bytes().take_while(u8::is_ascii_digit).count();
+    if len < min || len > max {
+        return None;
+    }
+    token[..len].parse().ok()
+}
+
+/// Parse `hh:mm:ss`, each part has one or two digits.
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = parts.next()?;
+    let minute = parts.next()?;
+    let second = parts.next()?;
+    let exact = |s: &str| match leading_digits(s, 1, 2) {
+        Some(v) if s.len() <= 2 => Some(v),
+        _ => None,
+    };
+    Some((exact(hour)?, exact(minute)?, leading_digits(second, 1, 2)?))
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    let prefix = token.get(..3)?;
+    MONTHS
+        .iter()
+        .position(|m| m.eq_ignore_ascii_case(prefix))
+        .map(|i| i as u32 + 1)
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn is_leap_year(year: u32) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+// The conversions between days since the epoch and civil dates are from
+// http://howardhinnant.github.io/date_algorithms.html.
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMF_FIXDATE: &str = "Sun, 06 Nov 1994 08:49:37 GMT";
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn formats_imf_fixdate() {
+        assert_eq!(fmt_http_date(at(784111777)), IMF_FIXDATE);
+        assert_eq!(fmt_http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            fmt_http_date(at(1) + Duration::from_millis(999)),
+            "Thu, 01 Jan 1970 00:00:01 GMT"
+        );
+        assert_eq!(
+            fmt_http_date(UNIX_EPOCH - Duration::from_millis(500)),
+            "Wed, 31 Dec 1969 23:59:59 GMT"
+        );
+        assert_eq!(
+            fmt_http_date(at(951782400)),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn parses_each_format() {
+        let expected = Ok(at(784111777));
+        for str in [
+            IMF_FIXDATE,
+            // RFC 850
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            // asctime
+            "Sun Nov  6 08:49:37 1994",
+            // other variants seen in Expires
+            "Sun, 06-Nov-1994 08:49:37 GMT",
+            "Sun, 6 Nov 1994 8:49:37 GMT",
+            "06 november 1994 08:49:37",
+        ] {
+            assert_eq!(parse_http_date(str), expected, "{}", str);
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for secs in [0, 68169600, 784111777, 951782400, 4102444799] {
+            assert_eq!(parse_http_date(&fmt_http_date(at(secs))), Ok(at(secs)));
+        }
+        let before_epoch = "Mon, 01 Jan 1601 00:00:00 GMT";
+        assert_eq!(
+            fmt_http_date(parse_http_date(before_epoch).unwrap()),
+            before_epoch
+        );
+    }
+
+    #[test]
+    fn windows_two_digit_years() {
+        let year = |y: &str| {
+            let date = parse_http_date(&format!("01 Jan {} 00:00:00", y)).unwrap();
+            fmt_http_date(date)[12..16].to_string()
+        };
+        assert_eq!(year("70"), "1970");
+        assert_eq!(year("99"), "1999");
+        assert_eq!(year("00"), "2000");
+        assert_eq!(year("69"), "2069");
+    }
+
+    #[test]
+    fn checks_leap_days() {
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"),
+            Ok(at(951782400))
+        );
+        assert!(parse_http_date("Thu, 29 Feb 2024 00:00:00 GMT").is_ok());
+        assert!(parse_http_date("Thu, 29 Feb 1900 00:00:00 GMT").is_err());
+        assert!(parse_http_date("Wed, 29 Feb 2023 00:00:00 GMT").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        for str in [
+            "",
+            "Sun, 31 Feb 1994 08:49:37 GMT",
+            "Sun, 31 Apr 1994 08:49:37 GMT",
+            "Sun, 00 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:60:00 GMT",
+            "Sun, 06 Nov 1994 08:49:60 GMT",
+            "Sun, 06 Nov 1600 08:49:37 GMT",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 GMT",
+            "Sun, 06 Nov 08:49:37 GMT",
+            "Sun, 123 Nov 1994 08:49:37 GMT",
+        ] {
+            assert_eq!(
+                parse_http_date(str),
+                Err(InvalidDate(str.to_string())),
+                "{}",
+                str
+            );
+        }
+        assert_eq!(
+            InvalidDate("x".to_string()).to_string(),
+            r#"invalid date "x""#
+        );
+    }
+}
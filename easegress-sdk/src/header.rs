@@ -0,0 +1,259 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+
+/// `HeaderMap` is a multi-value map of HTTP headers.
+///
+/// Lookups are case-insensitive, and the insertion order of the headers is preserved. Names and
+/// values added through `append` and `insert` are validated against RFC 9110.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+/// Error returned when adding a header whose name or value is not allowed by RFC 9110.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidHeader {
+    Name(String),
+    Value(String),
+}
+
+impl fmt::Display for InvalidHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidHeader::Name(name) => write!(f, "invalid header name {:?}", name),
+            InvalidHeader::Value(value) => write!(f, "invalid header value {:?}", value),
+        }
+    }
+}
+
+impl std::error::Error for InvalidHeader {}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+
+    /// Return the first value of header `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Return all values of header `name` in insertion order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Add a value to header `name`, keeping its existing values.
+    pub fn append(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = validate(name.into(), value.into())?;
+        self.entries.push((name, value));
+        Ok(())
+    }
+
+    /// Set header `name` to `value`, replacing its existing values in place.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = validate(name.into(), value.into())?;
+        match self
+            .entries
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(&name))
+        {
+            Some(pos) => {
+                let mut i = 0;
+                self.entries.retain(|(k, _)| {
+                    let keep = i <= pos || !k.eq_ignore_ascii_case(&name);
+                    i += 1;
+                    keep
+                });
+                self.entries[pos] = (name, value);
+            }
+            None => self.entries.push((name, value)),
+        }
+        Ok(())
+    }
+
+    /// Remove header `name`, returning its values.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.entries.retain(|(k, v)| {
+            if k.eq_ignore_ascii_case(name) {
+                removed.push(v.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Return the distinct header names in insertion order.
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = Vec::new();
+        for (k, _) in self.entries.iter() {
+            if !keys.iter().any(|x| x.eq_ignore_ascii_case(k)) {
+                keys.push(k);
+            }
+        }
+        keys
+    }
+
+    /// Iterate over all `(name, value)` pairs in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Add a header received from Easegress, which is trusted to be valid.
+    pub(crate) fn append_unchecked(&mut self, name: String, value: String) {
+        self.entries.push((name, value));
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+/// Validate a header to be sent to Easegress, which passes it on as is.
+pub(crate) fn check(name: &str, value: &str) -> Result<(), InvalidHeader> {
+    if !is_valid_name(name) {
+        return Err(InvalidHeader::Name(name.to_string()));
+    }
+    if !is_valid_value(value) {
+        return Err(InvalidHeader::Value(value.to_string()));
+    }
+    Ok(())
+}
+
+/// Like `check`, but log an invalid header as an error, for the setters which can not fail.
+pub(crate) fn check_or_log(name: &str, value: &str) -> bool {
+    match check(name, value) {
+        Ok(()) => true,
+        Err(e) => {
+            crate::log(crate::LogLevel::Error, format!("header is dropped: {}", e));
+            false
+        }
+    }
+}
+
+fn validate(name: String, value: String) -> Result<(String, String), InvalidHeader> {
+    if !is_valid_name(&name) {
+        return Err(InvalidHeader::Name(name));
+    }
+    if !is_valid_value(&value) {
+        return Err(InvalidHeader::Value(value));
+    }
+    Ok((name, value))
+}
+
+/// field-name = token, see RFC 9110 section 5.1.
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(is_tchar)
+}
+
+/// field-value = *field-content, see RFC 9110 section 5.5.
+pub(crate) fn is_valid_value(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    let is_ws = |b: &u8| *b == b' ' || *b == b'\t';
+    if bytes.first().map(is_ws).unwrap_or(false) || bytes.last().map(is_ws).unwrap_or(false) {
+        return false;
+    }
+    bytes
+        .iter()
+        .all(|b| is_ws(b) || (0x21..=0x7e).contains(b) || *b >= 0x80)
+}
+
+/// tchar, see RFC 9110 section 5.6.2.
+pub(crate) fn is_tchar(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::{self, MockHost, MockRequest, MockResponse};
+    use crate::request::{self, Request};
+    use crate::response::{self, Response};
+    use crate::LogLevel;
+
+    use super::InvalidHeader;
+
+    #[test]
+    fn setters_reject_invalid_headers() {
+        let host = MockHost::new(MockRequest::new("GET", "/"));
+        host.set_response(MockResponse::new(200));
+        host::set_host(host.clone());
+
+        request::set_header("X-Ok".to_string(), "yes".to_string());
+        request::set_header("X-Split".to_string(), "a\r\nX-Injected: b".to_string());
+        request::add_header("Bad Name".to_string(), "a".to_string());
+        response::resp_add_header("X-Split".to_string(), "a\nb".to_string());
+        assert_eq!(host.request().get_header("X-Ok"), Some("yes"));
+        assert_eq!(host.request().get_header("X-Split"), None);
+        assert_eq!(host.request().get_header("X-Injected"), None);
+        assert_eq!(host.request().headers.len(), 1);
+        assert_eq!(host.response().get_header("X-Split"), None);
+        let logs = host.logs();
+        assert_eq!(logs.len(), 3);
+        assert!(logs.iter().all(|(level, _)| *level == LogLevel::Error));
+
+        let mut req = Request::new();
+        assert_eq!(
+            req.set_header("X-Split", "a\rb"),
+            Err(InvalidHeader::Value("a\rb".to_string()))
+        );
+        assert_eq!(
+            req.add_header("", "a"),
+            Err(InvalidHeader::Name(String::new()))
+        );
+        assert_eq!(req.add_header("X-Ok", "again"), Ok(()));
+        assert_eq!(host.request().get_all_header("X-Ok"), vec!["yes", "again"]);
+
+        let mut resp = Response::new();
+        assert!(resp.set_header("X-Split", "a\0b").is_err());
+        assert_eq!(resp.set_header("X-Ok", "yes"), Ok(()));
+        assert_eq!(host.response().get_header("X-Ok"), Some("yes"));
+        host::reset_host();
+    }
+}
@@ -0,0 +1,689 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::host::{set_host, Host};
+use crate::marshal::unmarshal_all_header;
+use crate::request::escape_path;
+use crate::LogLevel;
+
+/// The request seen by a `Program` running on a `MockHost`.
+///
+/// Like in Easegress, cookies are not stored separately but parsed from the `Cookie` headers.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub real_ip: String,
+    pub scheme: String,
+    pub proto: String,
+    pub method: String,
+    pub host: String,
+    pub path: String,
+    pub query: String,
+    pub fragment: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockRequest {
+    pub fn new(method: &str, path: &str) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    pub fn with_query(mut self, query: &str) -> Self {
+        self.query = query.to_string();
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        add_header(&mut self.headers, name, value);
+        self
+    }
+
+    /// Add a cookie to the `Cookie` header.
+    pub fn with_cookie(mut self, name: &str, value: &str) -> Self {
+        self.add_cookie(name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Return the first value of header `name`, the lookup is case-insensitive.
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        get_header(&self.headers, name)
+    }
+
+    /// Return all values of header `name`, the lookup is case-insensitive.
+    pub fn get_all_header(&self, name: &str) -> Vec<&str> {
+        get_all_header(&self.headers, name)
+    }
+
+    /// Return the value of the first cookie named `name` in the `Cookie` headers.
+    pub fn get_cookie(&self, name: &str) -> Option<&str> {
+        self.cookies().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    /// Return the cookies in the `Cookie` headers in order, like `Request.Cookies` of Go.
+    pub fn cookies(&self) -> impl Iterator<Item = (&str, &str)> {
+        get_all_header(&self.headers, "Cookie")
+            .into_iter()
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                let value = value.trim_matches('"');
+                (!name.is_empty()).then_some((name, value))
+            })
+    }
+
+    /// Append a cookie to the `Cookie` header, like `Request.AddCookie` of Go.
+    fn add_cookie(&mut self, name: &str, value: &str) {
+        let pair = format!("{}={}", name, value);
+        match self
+            .headers
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case("Cookie"))
+        {
+            Some((_, v)) if !v.is_empty() => *v = format!("{}; {}", v, pair),
+            Some((_, v)) => *v = pair,
+            None => add_header(&mut self.headers, "Cookie", &pair),
+        }
+    }
+}
+
+impl Default for MockRequest {
+    fn default() -> Self {
+        Self {
+            real_ip: "127.0.0.1".to_string(),
+            scheme: "http".to_string(),
+            proto: "HTTP/1.1".to_string(),
+            method: "GET".to_string(),
+            host: "localhost".to_string(),
+            path: "/".to_string(),
+            query: "".to_string(),
+            fragment: "".to_string(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+/// The response produced by a `Program` running on a `MockHost`.
+///
+/// Cookies set by `response::resp_set_cookie` are stored as `Set-Cookie` headers.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status_code: i32,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn new(status_code: i32) -> Self {
+        Self {
+            status_code,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        add_header(&mut self.headers, name, value);
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Return the first value of header `name`, the lookup is case-insensitive.
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        get_header(&self.headers, name)
+    }
+
+    /// Return all values of header `name`, the lookup is case-insensitive.
+    pub fn get_all_header(&self, name: &str) -> Vec<&str> {
+        get_all_header(&self.headers, name)
+    }
+}
+
+impl Default for MockResponse {
+    fn default() -> Self {
+        Self {
+            status_code: 200,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    request: MockRequest,
+    response: MockResponse,
+    cluster: HashMap<String, Vec<u8>>,
+    tags: Vec<String>,
+    logs: Vec<(LogLevel, String)>,
+    unix_time_in_ms: i64,
+    rand: f64,
+}
+
+/// `MockHost` is an in-memory `Host` for running a `Program` natively, e.g. in unit tests.
+///
+/// Clones share the same state, so a test can keep a handle after installing the host and assert
+/// on the mutated request, response, tags and log lines once `run` returns.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use easegress_sdk::host::{MockHost, MockRequest};
+/// # use easegress_sdk::{request, Program};
+/// struct AddRequestHeader;
+///
+/// impl Program for AddRequestHeader {
+///     fn new(_params: HashMap<String, String>) -> Self {
+///         Self
+///     }
+///
+///     fn run(&self) -> i32 {
+///         let v = request::get_header("Foo".to_string());
+///         request::add_header("Wasm-Added".to_string(), v);
+///         0
+///     }
+/// }
+///
+/// let host = MockHost::new(MockRequest::new("GET", "/").with_header("Foo", "bar"));
+/// host.install();
+///
+/// let program = AddRequestHeader::new(HashMap::new());
+/// assert_eq!(program.run(), 0);
+/// assert_eq!(host.request().get_header("Wasm-Added"), Some("bar"));
+/// ```
+#[derive(Clone, Default)]
+pub struct MockHost {
+    state: Rc<RefCell<MockState>>,
+}
+
+impl MockHost {
+    pub fn new(request: MockRequest) -> Self {
+        let host = Self::default();
+        host.state.borrow_mut().request = request;
+        host
+    }
+
+    /// Install this host as the backend of the current thread, see `host::set_host`.
+    pub fn install(&self) {
+        set_host(self.clone());
+    }
+
+    pub fn request(&self) -> MockRequest {
+        self.state.borrow().request.clone()
+    }
+
+    pub fn set_request(&self, request: MockRequest) {
+        self.state.borrow_mut().request = request;
+    }
+
+    pub fn response(&self) -> MockResponse {
+        self.state.borrow().response.clone()
+    }
+
+    /// Replace the response, e.g. to emulate the one returned by the backend.
+    pub fn set_response(&self, response: MockResponse) {
+        self.state.borrow_mut().response = response;
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.state.borrow().tags.clone()
+    }
+
+    pub fn logs(&self) -> Vec<(LogLevel, String)> {
+        self.state.borrow().logs.clone()
+    }
+
+    pub fn set_unix_time_in_ms(&self, ms: i64) {
+        self.state.borrow_mut().unix_time_in_ms = ms;
+    }
+
+    /// Set the value returned by `rand`.
+    pub fn set_rand(&self, val: f64) {
+        self.state.borrow_mut().rand = val;
+    }
+}
+
+impl Host for MockHost {
+    fn add_tag(&self, tag: &str) {
+        self.state.borrow_mut().tags.push(tag.to_string());
+    }
+
+    fn log(&self, level: LogLevel, msg: &str) {
+        self.state.borrow_mut().logs.push((level, msg.to_string()));
+    }
+
+    fn get_unix_time_in_ms(&self) -> i64 {
+        self.state.borrow().unix_time_in_ms
+    }
+
+    fn rand(&self) -> f64 {
+        self.state.borrow().rand
+    }
+
+    fn req_get_real_ip(&self) -> String {
+        self.state.borrow().request.real_ip.clone()
+    }
+
+    fn req_get_scheme(&self) -> String {
+        self.state.borrow().request.scheme.clone()
+    }
+
+    fn req_get_proto(&self) -> String {
+        self.state.borrow().request.proto.clone()
+    }
+
+    fn req_get_method(&self) -> String {
+        self.state.borrow().request.method.clone()
+    }
+
+    fn req_set_method(&self, method: &str) {
+        self.state.borrow_mut().request.method = method.to_string();
+    }
+
+    fn req_get_host(&self) -> String {
+        self.state.borrow().request.host.clone()
+    }
+
+    fn req_set_host(&self, host: &str) {
+        self.state.borrow_mut().request.host = host.to_string();
+    }
+
+    fn req_get_path(&self) -> String {
+        self.state.borrow().request.path.clone()
+    }
+
+    fn req_set_path(&self, path: &str) {
+        self.state.borrow_mut().request.path = path.to_string();
+    }
+
+    fn req_get_escaped_path(&self) -> String {
+        escape_path(&self.state.borrow().request.path)
+    }
+
+    fn req_get_query(&self) -> String {
+        self.state.borrow().request.query.clone()
+    }
+
+    fn req_set_query(&self, query: &str) {
+        self.state.borrow_mut().request.query = query.to_string();
+    }
+
+    fn req_get_fragment(&self) -> String {
+        self.state.borrow().request.fragment.clone()
+    }
+
+    fn req_get_header(&self, name: &str) -> String {
+        let state = self.state.borrow();
+        get_header(&state.request.headers, name)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn req_get_all_header(&self) -> String {
+        format_header_block(&self.state.borrow().request.headers)
+    }
+
+    fn req_set_header(&self, name: &str, value: &str) {
+        set_header(&mut self.state.borrow_mut().request.headers, name, value);
+    }
+
+    fn req_set_all_header(&self, headers: &str) {
+        self.state.borrow_mut().request.headers = parse_header_block(headers);
+    }
+
+    fn req_add_header(&self, name: &str, value: &str) {
+        add_header(&mut self.state.borrow_mut().request.headers, name, value);
+    }
+
+    fn req_del_header(&self, name: &str) {
+        del_header(&mut self.state.borrow_mut().request.headers, name);
+    }
+
+    fn req_get_cookie(&self, name: &str) -> String {
+        let state = self.state.borrow();
+        match state.request.get_cookie(name) {
+            Some(value) => format!("{}={}", name, value),
+            None => "".to_string(),
+        }
+    }
+
+    fn req_get_all_cookie(&self) -> Vec<String> {
+        let state = self.state.borrow();
+        state
+            .request
+            .cookies()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect()
+    }
+
+    fn req_add_cookie(&self, cookie: &str) {
+        let pair = cookie.split(';').next().unwrap_or_default();
+        if let Some((name, value)) = pair.split_once('=') {
+            let mut state = self.state.borrow_mut();
+            state.request.add_cookie(name.trim(), value.trim());
+        }
+    }
+
+    fn req_get_body(&self) -> Vec<u8> {
+        self.state.borrow().request.body.clone()
+    }
+
+    fn req_set_body(&self, body: &[u8]) {
+        self.state.borrow_mut().request.body = body.to_vec();
+    }
+
+    fn resp_get_status_code(&self) -> i32 {
+        self.state.borrow().response.status_code
+    }
+
+    fn resp_set_status_code(&self, code: i32) {
+        self.state.borrow_mut().response.status_code = code;
+    }
+
+    fn resp_get_header(&self, name: &str) -> String {
+        let state = self.state.borrow();
+        get_header(&state.response.headers, name)
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn resp_get_all_header(&self) -> String {
+        format_header_block(&self.state.borrow().response.headers)
+    }
+
+    fn resp_set_header(&self, name: &str, value: &str) {
+        set_header(&mut self.state.borrow_mut().response.headers, name, value);
+    }
+
+    fn resp_set_all_header(&self, headers: &str) {
+        self.state.borrow_mut().response.headers = parse_header_block(headers);
+    }
+
+    fn resp_add_header(&self, name: &str, value: &str) {
+        add_header(&mut self.state.borrow_mut().response.headers, name, value);
+    }
+
+    fn resp_del_header(&self, name: &str) {
+        del_header(&mut self.state.borrow_mut().response.headers, name);
+    }
+
+    fn resp_set_cookie(&self, cookie: &str) {
+        add_header(
+            &mut self.state.borrow_mut().response.headers,
+            "Set-Cookie",
+            cookie,
+        );
+    }
+
+    fn resp_get_body(&self) -> Vec<u8> {
+        self.state.borrow().response.body.clone()
+    }
+
+    fn resp_set_body(&self, body: &[u8]) {
+        self.state.borrow_mut().response.body = body.to_vec();
+    }
+
+    fn cluster_get_binary(&self, key: &str) -> Vec<u8> {
+        let state = self.state.borrow();
+        state.cluster.get(key).cloned().unwrap_or_default()
+    }
+
+    fn cluster_put_binary(&self, key: &str, val: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        state.cluster.insert(key.to_string(), val.to_vec());
+    }
+
+    fn cluster_get_string(&self, key: &str) -> String {
+        String::from_utf8_lossy(&self.cluster_get_binary(key)).to_string()
+    }
+
+    fn cluster_put_string(&self, key: &str, val: &str) {
+        self.cluster_put_binary(key, val.as_bytes());
+    }
+
+    fn cluster_get_integer(&self, key: &str) -> i64 {
+        self.cluster_get_string(key).parse().unwrap_or_default()
+    }
+
+    fn cluster_put_integer(&self, key: &str, val: i64) {
+        self.cluster_put_string(key, &val.to_string());
+    }
+
+    fn cluster_add_integer(&self, key: &str, val: i64) -> i64 {
+        let val = self.cluster_get_integer(key) + val;
+        self.cluster_put_integer(key, val);
+        val
+    }
+
+    fn cluster_get_float(&self, key: &str) -> f64 {
+        self.cluster_get_string(key).parse().unwrap_or_default()
+    }
+
+    fn cluster_put_float(&self, key: &str, val: f64) {
+        self.cluster_put_string(key, &val.to_string());
+    }
+
+    fn cluster_add_float(&self, key: &str, val: f64) -> f64 {
+        let val = self.cluster_get_float(key) + val;
+        self.cluster_put_float(key, val);
+        val
+    }
+
+    fn cluster_count_key(&self, prefix: &str) -> i32 {
+        let state = self.state.borrow();
+        state
+            .cluster
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .count() as i32
+    }
+}
+
+/// Canonicalize a header name the way Easegress does, e.g. `content-type` to `Content-Type`.
+fn canonical_header_name(name: &str) -> String {
+    let mut upper = true;
+    name.chars()
+        .map(|c| {
+            let c = if upper {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            };
+            upper = c == '-';
+            c
+        })
+        .collect()
+}
+
+fn get_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn get_all_header<'a>(headers: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+    headers
+        .iter()
+        .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+        .collect()
+}
+
+fn add_header(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    headers.push((canonical_header_name(name), value.to_string()));
+}
+
+fn set_header(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    del_header(headers, name);
+    add_header(headers, name, value);
+}
+
+fn del_header(headers: &mut Vec<(String, String)>, name: &str) {
+    headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+}
+
+/// Format headers the way Easegress does, one `Name: Value` line per value.
+fn format_header_block(headers: &[(String, String)]) -> String {
+    let mut str = "".to_string();
+    for (name, value) in headers {
+        str += format!("{}: {}\r\n", name, value).as_str();
+    }
+    str
+}
+
+fn parse_header_block(str: &str) -> Vec<(String, String)> {
+    let mut headers = Vec::new();
+    for (name, value) in unmarshal_all_header(str) {
+        add_header(&mut headers, &name, &value);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::reset_host;
+    use crate::{cluster, request, response, Program, RunResult};
+
+    // a program limiting the requests of each client to `max`
+    struct Limiter {
+        max: i64,
+    }
+
+    impl Program for Limiter {
+        fn new(params: HashMap<String, String>) -> Self {
+            let max = params.get("max").and_then(|v| v.parse().ok()).unwrap_or(1);
+            Self { max }
+        }
+
+        fn run(&self) -> i32 {
+            let client = request::get_header("X-Client".to_string());
+            let count = cluster::add_integer(format!("limiter/{}", client), 1);
+            if count > self.max {
+                crate::log(LogLevel::Warning, format!("{} is limited", client));
+                response::resp_set_header("Retry-After".to_string(), "1".to_string());
+                return RunResult::Result1.into();
+            }
+            request::set_header("X-Count".to_string(), count.to_string());
+            RunResult::Continue.into()
+        }
+    }
+
+    #[test]
+    fn drives_a_program() {
+        let host = MockHost::new(MockRequest::new("GET", "/").with_header("X-Client", "a"));
+        host.install();
+
+        let params = HashMap::from([("max".to_string(), "2".to_string())]);
+        let program = Limiter::new(params);
+        assert_eq!(program.run(), 0);
+        assert_eq!(host.request().get_header("X-Count"), Some("1"));
+        assert_eq!(program.run(), 0);
+        assert_eq!(host.request().get_header("X-Count"), Some("2"));
+
+        assert_eq!(program.run(), 1);
+        assert_eq!(host.response().get_header("Retry-After"), Some("1"));
+        assert_eq!(
+            host.logs(),
+            vec![(LogLevel::Warning, "a is limited".to_string())]
+        );
+
+        // another client is counted separately
+        host.set_request(MockRequest::new("GET", "/").with_header("X-Client", "b"));
+        assert_eq!(program.run(), 0);
+        assert_eq!(host.request().get_header("X-Count"), Some("1"));
+        assert_eq!(host.cluster_count_key("limiter/"), 2);
+        reset_host();
+    }
+
+    #[test]
+    fn cookies_are_parsed_from_the_cookie_header() {
+        let request = MockRequest::new("GET", "/")
+            .with_header("Cookie", "x=1; y=\"2\"")
+            .with_cookie("sid", "abc");
+        assert_eq!(
+            request.get_all_header("Cookie"),
+            vec!["x=1; y=\"2\"; sid=abc"]
+        );
+
+        let host = MockHost::new(request);
+        assert_eq!(host.req_get_all_cookie(), vec!["x=1", "y=2", "sid=abc"]);
+        assert_eq!(host.req_get_cookie("sid"), "sid=abc");
+        assert_eq!(host.req_get_cookie("none"), "");
+        assert!(host.req_get_all_header().contains("sid=abc"));
+    }
+
+    #[test]
+    fn add_cookie_appends_to_the_cookie_header() {
+        let host = MockHost::new(MockRequest::new("GET", "/"));
+        host.req_add_cookie("a=1; Path=/");
+        host.req_add_cookie("b=2");
+        assert_eq!(host.req_get_header("cookie"), "a=1; b=2");
+        assert_eq!(host.request().get_cookie("b"), Some("2"));
+    }
+
+    #[test]
+    fn headers_are_canonicalized_and_case_insensitive() {
+        let host = MockHost::default();
+        host.req_add_header("x-foo", "1");
+        host.req_add_header("X-FOO", "2");
+        assert_eq!(host.req_get_header("X-Foo"), "1");
+        assert_eq!(host.req_get_all_header(), "X-Foo: 1\r\nX-Foo: 2\r\n");
+
+        host.req_set_header("x-foo", "3");
+        assert_eq!(host.request().get_all_header("x-foo"), vec!["3"]);
+        host.req_del_header("X-Foo");
+        assert_eq!(host.req_get_all_header(), "");
+
+        host.resp_set_all_header("content-type: text/plain\r\nX-A:  b \r\n");
+        assert_eq!(
+            host.response().get_header("Content-Type"),
+            Some("text/plain")
+        );
+        assert_eq!(host.resp_get_header("x-a"), "b");
+    }
+
+    #[test]
+    fn set_cookie_is_a_response_header() {
+        let host = MockHost::default();
+        host.resp_set_cookie("a=1; Path=/");
+        host.resp_set_cookie("b=2");
+        assert_eq!(
+            host.response().get_all_header("Set-Cookie"),
+            vec!["a=1; Path=/", "b=2"]
+        );
+    }
+
+    #[test]
+    fn cluster_values_are_shared_by_clones() {
+        let host = MockHost::default();
+        let clone = host.clone();
+        host.cluster_put_string("k", "v");
+        assert_eq!(clone.cluster_get_string("k"), "v");
+        assert_eq!(host.cluster_add_integer("n", 2), 2);
+        assert_eq!(clone.cluster_add_integer("n", 3), 5);
+        assert_eq!(host.cluster_add_float("f", 0.5), 0.5);
+        assert_eq!(host.cluster_count_key(""), 3);
+        assert_eq!(host.cluster_get_binary("missing"), Vec::<u8>::new());
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! The backend behind every SDK call.
+//!
+//! All functions in `request`, `response`, `cluster` and the crate root go through a `Host`.
+//! When compiled to `wasm32`, the default host forwards to the imports of the `easegress` module
+//! provided by the WasmHost filter. On other targets there is no default host, so a backend must be
+//! installed with `set_host` before calling into the SDK, which allows a `Program` to be driven
+//! natively, e.g. in unit tests with the in-memory `MockHost`.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::LogLevel;
+
+// filters never run on the mock, so it is not compiled into them
+#[cfg(not(target_arch = "wasm32"))]
+mod mock;
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use mock::{MockHost, MockRequest, MockResponse};
+#[cfg(target_arch = "wasm32")]
+pub use wasm::WasmHost;
+
+/// `Host` mirrors the imports of the `easegress` module, one method per `host_*` function.
+///
+/// Headers are exchanged as a header block (`Name:Value` lines separated by `\r\n`) and cookies as
+/// their string form, exactly as they cross the Wasm boundary. An empty string returned by
+/// `req_get_cookie` means the cookie does not exist.
+pub trait Host {
+    fn add_tag(&self, tag: &str);
+    fn log(&self, level: LogLevel, msg: &str);
+    fn get_unix_time_in_ms(&self) -> i64;
+    fn rand(&self) -> f64;
+
+    fn req_get_real_ip(&self) -> String;
+    fn req_get_scheme(&self) -> String;
+    fn req_get_proto(&self) -> String;
+    fn req_get_method(&self) -> String;
+    fn req_set_method(&self, method: &str);
+    fn req_get_host(&self) -> String;
+    fn req_set_host(&self, host: &str);
+    fn req_get_path(&self) -> String;
+    fn req_set_path(&self, path: &str);
+    fn req_get_escaped_path(&self) -> String;
+    fn req_get_query(&self) -> String;
+    fn req_set_query(&self, query: &str);
+    fn req_get_fragment(&self) -> String;
+    fn req_get_header(&self, name: &str) -> String;
+    fn req_get_all_header(&self) -> String;
+    fn req_set_header(&self, name: &str, value: &str);
+    fn req_set_all_header(&self, headers: &str);
+    fn req_add_header(&self, name: &str, value: &str);
+    fn req_del_header(&self, name: &str);
+    fn req_get_cookie(&self, name: &str) -> String;
+    fn req_get_all_cookie(&self) -> Vec<String>;
+    fn req_add_cookie(&self, cookie: &str);
+    fn req_get_body(&self) -> Vec<u8>;
+    fn req_set_body(&self, body: &[u8]);
+
+    fn resp_get_status_code(&self) -> i32;
+    fn resp_set_status_code(&self, code: i32);
+    fn resp_get_header(&self, name: &str) -> String;
+    fn resp_get_all_header(&self) -> String;
+    fn resp_set_header(&self, name: &str, value: &str);
+    fn resp_set_all_header(&self, headers: &str);
+    fn resp_add_header(&self, name: &str, value: &str);
+    fn resp_del_header(&self, name: &str);
+    fn resp_set_cookie(&self, cookie: &str);
+    fn resp_get_body(&self) -> Vec<u8>;
+    fn resp_set_body(&self, body: &[u8]);
+
+    fn cluster_get_binary(&self, key: &str) -> Vec<u8>;
+    fn cluster_put_binary(&self, key: &str, val: &[u8]);
+    fn cluster_get_string(&self, key: &str) -> String;
+    fn cluster_put_string(&self, key: &str, val: &str);
+    fn cluster_get_integer(&self, key: &str) -> i64;
+    fn cluster_put_integer(&self, key: &str, val: i64);
+    fn cluster_add_integer(&self, key: &str, val: i64) -> i64;
+    fn cluster_get_float(&self, key: &str) -> f64;
+    fn cluster_put_float(&self, key: &str, val: f64);
+    fn cluster_add_float(&self, key: &str, val: f64) -> f64;
+    fn cluster_count_key(&self, prefix: &str) -> i32;
+}
+
+thread_local! {
+    static HOST: RefCell<Option<Rc<dyn Host>>> = RefCell::new(None);
+}
+
+/// Install `host` as the backend for all SDK calls made from the current thread.
+pub fn set_host<H: Host + 'static>(host: H) {
+    HOST.with(|h| *h.borrow_mut() = Some(Rc::new(host)));
+}
+
+/// Remove the backend installed by `set_host`, falling back to the default one.
+pub fn reset_host() {
+    HOST.with(|h| *h.borrow_mut() = None);
+}
+
+pub(crate) fn with_host<R>(f: impl FnOnce(&dyn Host) -> R) -> R {
+    let host = HOST.with(|h| h.borrow().clone());
+    match host {
+        Some(host) => f(host.as_ref()),
+        #[cfg(target_arch = "wasm32")]
+        None => f(&WasmHost),
+        #[cfg(not(target_arch = "wasm32"))]
+        None => panic!("no Easegress host is installed, call `host::set_host` first"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn installed_host_receives_calls() {
+        let host = MockHost::default();
+        set_host(host.clone());
+        host.set_rand(0.25);
+
+        crate::add_tag("routed".to_string());
+        assert_eq!(host.tags(), vec!["routed".to_string()]);
+        assert_eq!(crate::rand(), 0.25);
+
+        // a newly installed host replaces the previous one
+        let other = MockHost::default();
+        set_host(other.clone());
+        crate::add_tag("other".to_string());
+        assert_eq!(host.tags().len(), 1);
+        assert_eq!(other.tags(), vec!["other".to_string()]);
+        reset_host();
+    }
+
+    #[test]
+    #[should_panic(expected = "no Easegress host is installed")]
+    fn panics_without_host() {
+        reset_host();
+        crate::rand();
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use crate::host::Host;
+use crate::marshal::{
+    marshal_data, marshal_string, unmarshal_data, unmarshal_string, unmarshal_string_vec,
+};
+use crate::LogLevel;
+
+#[link(wasm_import_module = "easegress")]
+extern "C" {
+    fn host_add_tag(addr: i32);
+    fn host_log(level: i32, msg: i32);
+    fn host_get_unix_time_in_ms() -> i64;
+    fn host_rand() -> f64;
+
+    fn host_req_get_real_ip() -> i32;
+    fn host_req_get_scheme() -> i32;
+    fn host_req_get_proto() -> i32;
+    fn host_req_get_method() -> i32;
+    fn host_req_set_method(addr: i32);
+    fn host_req_get_host() -> i32;
+    fn host_req_set_host(addr: i32);
+    fn host_req_get_path() -> i32;
+    fn host_req_set_path(addr: i32);
+    fn host_req_get_escaped_path() -> i32;
+    fn host_req_get_query() -> i32;
+    fn host_req_set_query(addr: i32);
+    fn host_req_get_fragment() -> i32;
+    fn host_req_get_header(addr: i32) -> i32;
+    fn host_req_get_all_header() -> i32;
+    fn host_req_set_header(name_addr: i32, value_addr: i32);
+    fn host_req_set_all_header(addr: i32);
+    fn host_req_add_header(name_addr: i32, value_addr: i32);
+    fn host_req_del_header(addr: i32);
+    fn host_req_get_cookie(addr: i32) -> i32;
+    fn host_req_get_all_cookie() -> i32;
+    fn host_req_add_cookie(addr: i32);
+    fn host_req_get_body() -> i32;
+    fn host_req_set_body(addr: i32);
+
+    fn host_resp_get_status_code() -> i32;
+    fn host_resp_set_status_code(code: i32);
+    fn host_resp_get_header(addr: i32) -> i32;
+    fn host_resp_get_all_header() -> i32;
+    fn host_resp_set_header(name_addr: i32, value_addr: i32);
+    fn host_resp_set_all_header(addr: i32);
+    fn host_resp_add_header(name_addr: i32, value_addr: i32);
+    fn host_resp_del_header(addr: i32);
+    fn host_resp_set_cookie(addr: i32);
+    fn host_resp_get_body() -> i32;
+    fn host_resp_set_body(addr: i32);
+
+    fn host_cluster_get_binary(addr: i32) -> i32;
+    fn host_cluster_put_binary(key_adddr: i32, val_addr: i32);
+    fn host_cluster_get_string(addr: i32) -> i32;
+    fn host_cluster_put_string(key_addr: i32, val_addr: i32);
+    fn host_cluster_get_integer(addr: i32) -> i64;
+    fn host_cluster_put_integer(addr: i32, val: i64);
+    fn host_cluster_add_integer(addr: i32, val: i64) -> i64;
+    fn host_cluster_get_float(addr: i32) -> f64;
+    fn host_cluster_put_float(addr: i32, val: f64);
+    fn host_cluster_add_float(addr: i32, val: f64) -> f64;
+    fn host_cluster_count_key(addr: i32) -> i32;
+}
+
+/// `WasmHost` forwards every call to the imports provided by the Easegress WasmHost filter.
+pub struct WasmHost;
+
+impl Host for WasmHost {
+    fn add_tag(&self, tag: &str) {
+        let data = marshal_string(tag);
+        unsafe { host_add_tag(data.as_ptr() as i32) }
+    }
+
+    fn log(&self, level: LogLevel, msg: &str) {
+        let data = marshal_string(msg);
+        unsafe { host_log(level as i32, data.as_ptr() as i32) }
+    }
+
+    fn get_unix_time_in_ms(&self) -> i64 {
+        unsafe { host_get_unix_time_in_ms() }
+    }
+
+    fn rand(&self) -> f64 {
+        unsafe { host_rand() }
+    }
+
+    fn req_get_real_ip(&self) -> String {
+        let ptr = unsafe { host_req_get_real_ip() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_scheme(&self) -> String {
+        let ptr = unsafe { host_req_get_scheme() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_proto(&self) -> String {
+        let ptr = unsafe { host_req_get_proto() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_method(&self) -> String {
+        let ptr = unsafe { host_req_get_method() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_set_method(&self, method: &str) {
+        let ptr = marshal_string(method);
+        unsafe { host_req_set_method(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_host(&self) -> String {
+        let ptr = unsafe { host_req_get_host() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_set_host(&self, host: &str) {
+        let ptr = marshal_string(host);
+        unsafe { host_req_set_host(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_path(&self) -> String {
+        let ptr = unsafe { host_req_get_path() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_set_path(&self, path: &str) {
+        let ptr = marshal_string(path);
+        unsafe { host_req_set_path(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_escaped_path(&self) -> String {
+        let ptr = unsafe { host_req_get_escaped_path() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_query(&self) -> String {
+        let ptr = unsafe { host_req_get_query() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_set_query(&self, query: &str) {
+        let ptr = marshal_string(query);
+        unsafe { host_req_set_query(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_fragment(&self) -> String {
+        let ptr = unsafe { host_req_get_fragment() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_header(&self, name: &str) -> String {
+        let name_ptr = marshal_string(name);
+        let ptr = unsafe { host_req_get_header(name_ptr.as_ptr() as i32) };
+        unmarshal_string(ptr)
+    }
+
+    fn req_get_all_header(&self) -> String {
+        let ptr = unsafe { host_req_get_all_header() };
+        unmarshal_string(ptr)
+    }
+
+    fn req_set_header(&self, name: &str, value: &str) {
+        let name_ptr = marshal_string(name);
+        let value_ptr = marshal_string(value);
+        unsafe { host_req_set_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    }
+
+    fn req_set_all_header(&self, headers: &str) {
+        let ptr = marshal_string(headers);
+        unsafe { host_req_set_all_header(ptr.as_ptr() as i32) }
+    }
+
+    fn req_add_header(&self, name: &str, value: &str) {
+        let name_ptr = marshal_string(name);
+        let value_ptr = marshal_string(value);
+        unsafe { host_req_add_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    }
+
+    fn req_del_header(&self, name: &str) {
+        let ptr = marshal_string(name);
+        unsafe { host_req_del_header(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_cookie(&self, name: &str) -> String {
+        let ptr = marshal_string(name);
+        let data = unsafe { host_req_get_cookie(ptr.as_ptr() as i32) };
+        unmarshal_string(data)
+    }
+
+    fn req_get_all_cookie(&self) -> Vec<String> {
+        let ptr = unsafe { host_req_get_all_cookie() };
+        unmarshal_string_vec(ptr)
+    }
+
+    fn req_add_cookie(&self, cookie: &str) {
+        let ptr = marshal_string(cookie);
+        unsafe { host_req_add_cookie(ptr.as_ptr() as i32) }
+    }
+
+    fn req_get_body(&self) -> Vec<u8> {
+        let ptr = unsafe { host_req_get_body() };
+        unmarshal_data(ptr)
+    }
+
+    fn req_set_body(&self, body: &[u8]) {
+        let ptr = marshal_data(body);
+        unsafe { host_req_set_body(ptr.as_ptr() as i32) }
+    }
+
+    fn resp_get_status_code(&self) -> i32 {
+        unsafe { host_resp_get_status_code() }
+    }
+
+    fn resp_set_status_code(&self, code: i32) {
+        unsafe { host_resp_set_status_code(code) }
+    }
+
+    fn resp_get_header(&self, name: &str) -> String {
+        let ptr = marshal_string(name);
+        let data = unsafe { host_resp_get_header(ptr.as_ptr() as i32) };
+        unmarshal_string(data)
+    }
+
+    fn resp_get_all_header(&self) -> String {
+        let ptr = unsafe { host_resp_get_all_header() };
+        unmarshal_string(ptr)
+    }
+
+    fn resp_set_header(&self, name: &str, value: &str) {
+        let name_ptr = marshal_string(name);
+        let value_ptr = marshal_string(value);
+        unsafe { host_resp_set_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    }
+
+    fn resp_set_all_header(&self, headers: &str) {
+        let ptr = marshal_string(headers);
+        unsafe { host_resp_set_all_header(ptr.as_ptr() as i32) }
+    }
+
+    fn resp_add_header(&self, name: &str, value: &str) {
+        let name_ptr = marshal_string(name);
+        let value_ptr = marshal_string(value);
+        unsafe { host_resp_add_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    }
+
+    fn resp_del_header(&self, name: &str) {
+        let ptr = marshal_string(name);
+        unsafe { host_resp_del_header(ptr.as_ptr() as i32) }
+    }
+
+    fn resp_set_cookie(&self, cookie: &str) {
+        let ptr = marshal_string(cookie);
+        unsafe { host_resp_set_cookie(ptr.as_ptr() as i32) }
+    }
+
+    fn resp_get_body(&self) -> Vec<u8> {
+        let ptr = unsafe { host_resp_get_body() };
+        unmarshal_data(ptr)
+    }
+
+    fn resp_set_body(&self, body: &[u8]) {
+        let ptr = marshal_data(body);
+        unsafe { host_resp_set_body(ptr.as_ptr() as i32) }
+    }
+
+    fn cluster_get_binary(&self, key: &str) -> Vec<u8> {
+        let v = marshal_string(key);
+        let data = unsafe { host_cluster_get_binary(v.as_ptr() as i32) };
+        unmarshal_data(data)
+    }
+
+    fn cluster_put_binary(&self, key: &str, val: &[u8]) {
+        let ptr_key = marshal_string(key);
+        let ptr_val = marshal_data(val);
+        unsafe { host_cluster_put_binary(ptr_key.as_ptr() as i32, ptr_val.as_ptr() as i32) }
+    }
+
+    fn cluster_get_string(&self, key: &str) -> String {
+        let v = marshal_string(key);
+        let data = unsafe { host_cluster_get_string(v.as_ptr() as i32) };
+        unmarshal_string(data)
+    }
+
+    fn cluster_put_string(&self, key: &str, val: &str) {
+        let ptr_key = marshal_string(key);
+        let ptr_val = marshal_string(val);
+        unsafe { host_cluster_put_string(ptr_key.as_ptr() as i32, ptr_val.as_ptr() as i32) }
+    }
+
+    fn cluster_get_integer(&self, key: &str) -> i64 {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_get_integer(ptr.as_ptr() as i32) }
+    }
+
+    fn cluster_put_integer(&self, key: &str, val: i64) {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_put_integer(ptr.as_ptr() as i32, val) }
+    }
+
+    fn cluster_add_integer(&self, key: &str, val: i64) -> i64 {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_add_integer(ptr.as_ptr() as i32, val) }
+    }
+
+    fn cluster_get_float(&self, key: &str) -> f64 {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_get_float(ptr.as_ptr() as i32) }
+    }
+
+    fn cluster_put_float(&self, key: &str, val: f64) {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_put_float(ptr.as_ptr() as i32, val) }
+    }
+
+    fn cluster_add_float(&self, key: &str, val: f64) -> f64 {
+        let ptr = marshal_string(key);
+        unsafe { host_cluster_add_float(ptr.as_ptr() as i32, val) }
+    }
+
+    fn cluster_count_key(&self, prefix: &str) -> i32 {
+        let v = marshal_string(prefix);
+        unsafe { host_cluster_count_key(v.as_ptr() as i32) }
+    }
+}
@@ -0,0 +1,220 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Interoperability with the types of the `http` crate, enabled by the `http` feature.
+//!
+//! ```ignore
+//! let req = http_compat::to_http_request()?;
+//! if let Some(resp) = middleware.check(&req) {
+//!     http_compat::apply_http_response(&resp)?;
+//!     return 1;
+//! }
+//! ```
+
+use std::convert::TryFrom;
+
+use crate::header::{HeaderMap, InvalidHeader};
+use crate::method::Method;
+use crate::request::Request;
+use crate::response::Response;
+use crate::status::StatusCode;
+
+/// Build an `http::Request` from the request being processed.
+pub fn to_http_request() -> Result<http::Request<Vec<u8>>, http::Error> {
+    let req = Request::new();
+    let mut uri = format!("{}://{}{}", req.scheme(), req.host(), req.escaped_path());
+    if !req.query().is_empty() {
+        uri += "?";
+        uri += req.query();
+    }
+
+    let mut builder = http::Request::builder()
+        .method(req.method())
+        .uri(uri)
+        .version(to_http_version(req.proto()));
+    for (name, value) in req.headers() {
+        builder = builder.header(name, value);
+    }
+    builder.body(req.body().to_vec())
+}
+
+/// Set the status code, headers and body of the response from `resp`.
+///
+/// The headers of `resp` replace all existing headers of the response. If one of them is not valid
+/// UTF-8 or not allowed by RFC 9110, the response is left unchanged.
+pub fn apply_http_response<B: AsRef<[u8]>>(resp: &http::Response<B>) -> Result<(), InvalidHeader> {
+    let headers = HeaderMap::try_from(resp.headers())?;
+    let mut r = Response::new();
+    r.set_status_code(StatusCode::from(resp.status()));
+    r.set_headers(&headers);
+    r.set_body(resp.body().as_ref());
+    Ok(())
+}
+
+fn to_http_version(proto: &str) -> http::Version {
+    match proto {
+        "HTTP/0.9" => http::Version::HTTP_09,
+        "HTTP/1.0" => http::Version::HTTP_10,
+        "HTTP/2" | "HTTP/2.0" => http::Version::HTTP_2,
+        "HTTP/3" | "HTTP/3.0" => http::Version::HTTP_3,
+        _ => http::Version::HTTP_11,
+    }
+}
+
+impl TryFrom<&HeaderMap> for http::HeaderMap {
+    type Error = http::Error;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self, Self::Error> {
+        let mut result = http::HeaderMap::with_capacity(headers.len());
+        for (name, value) in headers {
+            let name = http::HeaderName::try_from(name)?;
+            let value = http::HeaderValue::try_from(value)?;
+            result.append(name, value);
+        }
+        Ok(result)
+    }
+}
+
+impl TryFrom<&http::HeaderMap> for HeaderMap {
+    type Error = InvalidHeader;
+
+    /// Fail on a value which is not valid UTF-8, holding it converted lossily, or which is not
+    /// allowed by RFC 9110.
+    fn try_from(headers: &http::HeaderMap) -> Result<Self, Self::Error> {
+        let mut result = HeaderMap::new();
+        for (name, value) in headers {
+            let value = std::str::from_utf8(value.as_bytes()).map_err(|_| {
+                InvalidHeader::Value(String::from_utf8_lossy(value.as_bytes()).to_string())
+            })?;
+            result.append(name.as_str(), value)?;
+        }
+        Ok(result)
+    }
+}
+
+impl TryFrom<&Method> for http::Method {
+    type Error = http::method::InvalidMethod;
+
+    fn try_from(method: &Method) -> Result<Self, Self::Error> {
+        http::Method::from_bytes(method.as_str().as_bytes())
+    }
+}
+
+impl From<&http::Method> for Method {
+    fn from(method: &http::Method) -> Self {
+        Method::from_trusted(method.as_str())
+    }
+}
+
+impl From<StatusCode> for http::StatusCode {
+    fn from(code: StatusCode) -> Self {
+        // both accept 100..=999
+        http::StatusCode::from_u16(code.as_u16()).unwrap()
+    }
+}
+
+impl From<http::StatusCode> for StatusCode {
+    fn from(code: http::StatusCode) -> Self {
+        StatusCode::from_u16(code.as_u16()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{reset_host, MockHost, MockRequest, MockResponse};
+
+    #[test]
+    fn converts_the_request() {
+        let host = MockHost::new(
+            MockRequest::new("PATCH", "/a b")
+                .with_host("example.com:8080")
+                .with_query("x=1")
+                .with_header("Foo", "1")
+                .with_header("foo", "2")
+                .with_body("body"),
+        );
+        host.install();
+
+        let req = to_http_request().unwrap();
+        assert_eq!(req.method(), http::Method::PATCH);
+        assert_eq!(req.uri(), "http://example.com:8080/a%20b?x=1");
+        assert_eq!(req.version(), http::Version::HTTP_11);
+        let foo: Vec<_> = req.headers().get_all("foo").iter().collect();
+        assert_eq!(foo, vec!["1", "2"]);
+        assert_eq!(req.body(), b"body");
+
+        assert_eq!(Method::from(req.method()), Method::Patch);
+        assert_eq!(
+            http::Method::try_from(&Method::Patch).unwrap(),
+            http::Method::PATCH
+        );
+        reset_host();
+    }
+
+    #[test]
+    fn applies_the_response() {
+        let host = MockHost::new(MockRequest::new("GET", "/"));
+        host.install();
+        host.set_response(MockResponse::new(200).with_header("Old", "1"));
+
+        let resp = http::Response::builder()
+            .status(http::StatusCode::CREATED)
+            .header("Location", "/items/1")
+            .header("Set-Cookie", "a=1")
+            .header("Set-Cookie", "b=2")
+            .body("created")
+            .unwrap();
+        apply_http_response(&resp).unwrap();
+
+        let r = host.response();
+        assert_eq!(r.status_code, 201);
+        assert_eq!(r.get_header("Old"), None);
+        assert_eq!(r.get_header("location"), Some("/items/1"));
+        assert_eq!(r.get_all_header("Set-Cookie"), vec!["a=1", "b=2"]);
+        assert_eq!(r.body, b"created");
+        reset_host();
+    }
+
+    #[test]
+    fn rejects_invalid_response_headers() {
+        let host = MockHost::new(MockRequest::new("GET", "/"));
+        host.install();
+        host.set_response(MockResponse::new(200).with_header("Old", "1"));
+
+        for (value, err) in [(&b"a\xffb"[..], "a\u{FFFD}b"), (&b" padded"[..], " padded")] {
+            let resp = http::Response::builder()
+                .status(http::StatusCode::BAD_GATEWAY)
+                .header("X-Bad", http::HeaderValue::from_bytes(value).unwrap())
+                .body("")
+                .unwrap();
+            assert_eq!(
+                apply_http_response(&resp),
+                Err(InvalidHeader::Value(err.to_string()))
+            );
+        }
+        let r = host.response();
+        assert_eq!(r.status_code, 200);
+        assert_eq!(r.get_header("Old"), Some("1"));
+        reset_host();
+    }
+
+    #[test]
+    fn round_trips_header_maps() {
+        let mut headers = HeaderMap::new();
+        headers.append("Accept", "text/html").unwrap();
+        headers.append("X-Utf8", "ü").unwrap();
+        headers.append("accept", "*/*").unwrap();
+
+        let converted = http::HeaderMap::try_from(&headers).unwrap();
+        assert_eq!(converted.get_all("Accept").iter().count(), 2);
+        let back = HeaderMap::try_from(&converted).unwrap();
+        assert_eq!(
+            back.get_all("Accept").collect::<Vec<_>>(),
+            vec!["text/html", "*/*"]
+        );
+        assert_eq!(back.get("x-utf8"), Some("ü"));
+
+        let code = StatusCode::from_u16(404).unwrap();
+        assert_eq!(StatusCode::from(http::StatusCode::from(code)), code);
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! JSON bodies, enabled by the `serde_json` feature.
+//!
+//! ```ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Order {
+//!     id: u64,
+//!     #[serde(default)]
+//!     note: String,
+//! }
+//!
+//! let mut order: Order = match request::get_json() {
+//!     Ok(order) => order,
+//!     Err(e) => return response::respond(StatusCode::BAD_REQUEST).text(e.to_string()).send(),
+//! };
+//! order.note.clear();
+//! request::set_json(&order)?;
+//! ```
+//!
+//! Bodies can also be rewritten without a Rust type for the whole payload, by applying a JSON
+//! Patch (RFC 6902) whose paths are JSON Pointers (RFC 6901). A `Patch` can be decoded from the
+//! `parameters` of the spec:
+//!
+//! ```ignore
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     // e.g. '[{"op": "remove", "path": "/internal"}]'
+//!     response_patch: Patch,
+//! }
+//!
+//! fn run(&self) -> i32 {
+//!     if let Err(e) = response::resp_patch_json(&self.config.response_patch) {
+//!         log(LogLevel::Warning, e.to_string());
+//!     }
+//!     0
+//! }
+//! ```
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+mod patch;
+mod pointer;
+
+pub use patch::{Operation, Patch, PatchError};
+pub use pointer::Pointer;
+
+/// Error returned by the JSON body functions.
+#[derive(Debug)]
+pub enum JsonError {
+    /// The Content-Type is missing or is not a JSON media type, it holds the Content-Type.
+    ContentType(String),
+    /// The body is not valid JSON or does not match the target type.
+    Body(serde_json::Error),
+    /// The value can not be serialized.
+    Serialize(serde_json::Error),
+    /// A JSON Patch can not be applied to the body.
+    Patch(PatchError),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::ContentType(ct) if ct.is_empty() => write!(f, "missing JSON Content-Type"),
+            JsonError::ContentType(ct) => write!(f, "unexpected Content-Type {:?}", ct),
+            JsonError::Body(e) => write!(f, "malformed JSON body: {}", e),
+            JsonError::Serialize(e) => write!(f, "failed to serialize JSON body: {}", e),
+            JsonError::Patch(e) => write!(f, "failed to patch JSON body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JsonError::ContentType(_) => None,
+            JsonError::Body(e) | JsonError::Serialize(e) => Some(e),
+            JsonError::Patch(e) => Some(e),
+        }
+    }
+}
+
+/// The Content-Type set by `set_json` and `resp_set_json`.
+pub(crate) const CONTENT_TYPE: &str = "application/json";
+
+/// Report whether `content_type` is `application/json` or `application/*+json`, parameters like
+/// `charset` are ignored.
+pub fn is_json_content_type(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((ty, subtype)) = media_type.split_once('/') else {
+        return false;
+    };
+    let subtype = subtype.to_ascii_lowercase();
+    ty.eq_ignore_ascii_case("application") && (subtype == "json" || subtype.ends_with("+json"))
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(content_type: &str, body: &[u8]) -> Result<T, JsonError> {
+    if !is_json_content_type(content_type) {
+        return Err(JsonError::ContentType(content_type.to_string()));
+    }
+    serde_json::from_slice(body).map_err(JsonError::Body)
+}
+
+pub(crate) fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, JsonError> {
+    serde_json::to_vec(value).map_err(JsonError::Serialize)
+}
+
+pub(crate) fn patch(content_type: &str, body: &[u8], patch: &Patch) -> Result<Vec<u8>, JsonError> {
+    let mut doc: serde_json::Value = decode(content_type, body)?;
+    patch.apply(&mut doc).map_err(JsonError::Patch)?;
+    encode(&doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::host::{MockHost, MockRequest, MockResponse};
+    use crate::{request, response};
+
+    #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct Order {
+        id: u64,
+        #[serde(default)]
+        note: String,
+    }
+
+    #[test]
+    fn checks_content_types() {
+        assert!(is_json_content_type("application/json"));
+        assert!(is_json_content_type("Application/JSON; charset=utf-8"));
+        assert!(is_json_content_type("application/problem+json"));
+        assert!(!is_json_content_type(""));
+        assert!(!is_json_content_type("text/json"));
+        assert!(!is_json_content_type("application/jsonx"));
+        assert!(!is_json_content_type("application"));
+    }
+
+    #[test]
+    fn decodes_request_body() {
+        let host = MockHost::new(
+            MockRequest::new("POST", "/orders")
+                .with_header("Content-Type", "application/json; charset=utf-8")
+                .with_body(r#"{"id": 7}"#),
+        );
+        host.install();
+
+        let order: Order = request::get_json().unwrap();
+        assert_eq!(
+            order,
+            Order {
+                id: 7,
+                note: "".to_string()
+            }
+        );
+
+        host.set_request(
+            MockRequest::new("POST", "/orders")
+                .with_header("Content-Type", "application/json")
+                .with_body(r#"{"id": "7"}"#),
+        );
+        let err = request::get_json::<Order>().unwrap_err();
+        assert!(matches!(err, JsonError::Body(_)));
+        crate::host::reset_host();
+    }
+
+    #[test]
+    fn rejects_other_content_types() {
+        let host = MockHost::new(
+            MockRequest::new("POST", "/orders")
+                .with_header("Content-Type", "text/plain")
+                .with_body(r#"{"id": 7}"#),
+        );
+        host.install();
+
+        let err = request::get_json::<Order>().unwrap_err();
+        assert!(matches!(&err, JsonError::ContentType(ct) if ct == "text/plain"));
+        assert_eq!(err.to_string(), r#"unexpected Content-Type "text/plain""#);
+
+        host.set_request(MockRequest::new("POST", "/orders").with_body(r#"{"id": 7}"#));
+        let err = request::get_json::<Order>().unwrap_err();
+        assert_eq!(err.to_string(), "missing JSON Content-Type");
+        crate::host::reset_host();
+    }
+
+    #[test]
+    fn sets_content_type_when_encoding() {
+        let host = MockHost::new(
+            MockRequest::new("POST", "/orders").with_header("Content-Type", "text/plain"),
+        );
+        host.install();
+        host.set_response(MockResponse::new(200).with_header("Content-Type", "text/html"));
+
+        let order = Order {
+            id: 1,
+            note: "fragile".to_string(),
+        };
+        request::set_json(&order).unwrap();
+        let req = host.request();
+        assert_eq!(req.get_all_header("Content-Type"), vec![CONTENT_TYPE]);
+        assert_eq!(req.body, br#"{"id":1,"note":"fragile"}"#);
+
+        response::resp_set_json(&order).unwrap();
+        let resp = host.response();
+        assert_eq!(resp.get_all_header("Content-Type"), vec![CONTENT_TYPE]);
+        assert_eq!(response::resp_get_json::<Order>().unwrap(), order);
+        crate::host::reset_host();
+    }
+
+    #[test]
+    fn patches_bodies() {
+        let host = MockHost::new(
+            MockRequest::new("POST", "/orders")
+                .with_header("Content-Type", "application/vnd.api+json")
+                .with_body(r#"{"id": 1, "internal": true}"#),
+        );
+        host.install();
+
+        let patch: Patch = r#"[{"op": "remove", "path": "/internal"}]"#.parse().unwrap();
+        request::patch_json(&patch).unwrap();
+        let req = host.request();
+        assert_eq!(
+            req.get_header("Content-Type"),
+            Some("application/vnd.api+json")
+        );
+        assert_eq!(
+            serde_json::from_slice::<Value>(&req.body).unwrap(),
+            json!({"id": 1})
+        );
+
+        // the body is kept if the patch fails
+        let err = request::patch_json(&patch).unwrap_err();
+        assert!(matches!(err, JsonError::Patch(PatchError::Path(_))));
+        assert_eq!(host.request().body, req.body);
+        crate::host::reset_host();
+    }
+}
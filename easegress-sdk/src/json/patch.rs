@@ -0,0 +1,462 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde_json::{Number, Value};
+
+use super::pointer::{parse_index, Pointer};
+
+/// `Patch` is a JSON Patch document, see RFC 6902.
+///
+/// It can be parsed from its JSON text, and deserialized from either a sequence of operations or
+/// a string holding the JSON text, which is how it appears in the `parameters` of the spec.
+///
+/// ```ignore
+/// let patch: Patch = r#"[
+///     {"op": "remove", "path": "/password"},
+///     {"op": "add", "path": "/source", "value": "easegress"}
+/// ]"#.parse()?;
+/// patch.apply(&mut doc)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch(pub Vec<Operation>);
+
+/// `Operation` is an operation of a JSON Patch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { path: Pointer, value: Value },
+    Remove { path: Pointer },
+    Replace { path: Pointer, value: Value },
+    Move { from: Pointer, path: Pointer },
+    Copy { from: Pointer, path: Pointer },
+    Test { path: Pointer, value: Value },
+}
+
+/// Error returned when parsing or applying a JSON Patch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// A JSON Pointer is malformed.
+    Pointer(String),
+    /// The patch document is malformed.
+    Operation(String),
+    /// The target of an operation does not exist or can not be added.
+    Path(String),
+    /// A `test` operation failed.
+    Test(String),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Pointer(p) => write!(f, "invalid JSON pointer {:?}", p),
+            PatchError::Operation(msg) => write!(f, "invalid JSON patch: {}", msg),
+            PatchError::Path(p) => write!(f, "path {:?} does not exist", p),
+            PatchError::Test(p) => write!(f, "test of path {:?} failed", p),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl Patch {
+    /// Decode a patch from its JSON value, which must be an array of operations.
+    pub fn from_value(value: &Value) -> Result<Self, PatchError> {
+        let Value::Array(ops) = value else {
+            return Err(PatchError::Operation("expected an array".to_string()));
+        };
+        ops.iter()
+            .map(Operation::from_value)
+            .collect::<Result<_, _>>()
+            .map(Patch)
+    }
+
+    /// Apply the operations in order. If any of them fails, `doc` is left unchanged.
+    pub fn apply(&self, doc: &mut Value) -> Result<(), PatchError> {
+        let mut patched = doc.clone();
+        for op in self.0.iter() {
+            op.apply(&mut patched)?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+}
+
+impl Operation {
+    pub fn from_value(value: &Value) -> Result<Self, PatchError> {
+        let Value::Object(map) = value else {
+            return Err(PatchError::Operation("expected an object".to_string()));
+        };
+        let field = |name: &str| {
+            map.get(name)
+                .ok_or_else(|| PatchError::Operation(format!("missing `{}`", name)))
+        };
+        let pointer = |name: &str| match field(name)? {
+            Value::String(str) => Pointer::parse(str),
+            _ => Err(PatchError::Operation(format!("`{}` is not a string", name))),
+        };
+
+        let op = match field("op")? {
+            Value::String(op) => op.as_str(),
+            _ => return Err(PatchError::Operation("`op` is not a string".to_string())),
+        };
+        let op = match op {
+            "add" => Operation::Add {
+                path: pointer("path")?,
+                value: field("value")?.clone(),
+            },
+            "remove" => Operation::Remove {
+                path: pointer("path")?,
+            },
+            "replace" => Operation::Replace {
+                path: pointer("path")?,
+                value: field("value")?.clone(),
+            },
+            "move" => Operation::Move {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            "copy" => Operation::Copy {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            "test" => Operation::Test {
+                path: pointer("path")?,
+                value: field("value")?.clone(),
+            },
+            _ => return Err(PatchError::Operation(format!("unknown op {:?}", op))),
+        };
+        Ok(op)
+    }
+
+    fn apply(&self, doc: &mut Value) -> Result<(), PatchError> {
+        match self {
+            Operation::Add { path, value } => add(doc, path, value.clone()),
+            Operation::Remove { path } => remove(doc, path).map(|_| ()),
+            Operation::Replace { path, value } => {
+                let target = path
+                    .get_mut(doc)
+                    .ok_or_else(|| PatchError::Path(path.to_string()))?;
+                *target = value.clone();
+                Ok(())
+            }
+            Operation::Move { from, path } => {
+                if from.is_proper_prefix_of(path) {
+                    return Err(PatchError::Operation(format!(
+                        "can not move {:?} into its child {:?}",
+                        from.to_string(),
+                        path.to_string()
+                    )));
+                }
+                let value = remove(doc, from)?;
+                add(doc, path, value)
+            }
+            Operation::Copy { from, path } => {
+                let value = from
+                    .get(doc)
+                    .ok_or_else(|| PatchError::Path(from.to_string()))?
+                    .clone();
+                add(doc, path, value)
+            }
+            Operation::Test { path, value } => match path.get(doc) {
+                Some(v) if equal(v, value) => Ok(()),
+                _ => Err(PatchError::Test(path.to_string())),
+            },
+        }
+    }
+}
+
+/// Compare values as the `test` operation does, numbers are equal if their values are equal, so
+/// `1` equals `1.0`, and objects regardless of the order of their members.
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => equal_numbers(a, b),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(k, a)| b.get(k).map(|b| equal(a, b)).unwrap_or(false))
+        }
+        _ => a == b,
+    }
+}
+
+fn equal_numbers(a: &Number, b: &Number) -> bool {
+    // integers are compared exactly, as they may not fit in an f64
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        return a == b;
+    }
+    a.as_f64() == b.as_f64()
+}
+
+fn add(doc: &mut Value, path: &Pointer, value: Value) -> Result<(), PatchError> {
+    let Some((parent, last)) = path.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+    let not_found = || PatchError::Path(path.to_string());
+    match parent.get_mut(doc).ok_or_else(not_found)? {
+        Value::Object(map) => {
+            map.insert(last.to_string(), value);
+        }
+        Value::Array(array) => {
+            let index = match last {
+                "-" => array.len(),
+                _ => parse_index(last).ok_or_else(not_found)?,
+            };
+            if index > array.len() {
+                return Err(not_found());
+            }
+            array.insert(index, value);
+        }
+        _ => return Err(not_found()),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &Pointer) -> Result<Value, PatchError> {
+    let not_found = || PatchError::Path(path.to_string());
+    let (parent, last) = path
+        .split_last()
+        .ok_or_else(|| PatchError::Operation("can not remove the whole document".to_string()))?;
+    match parent.get_mut(doc).ok_or_else(not_found)? {
+        Value::Object(map) => map.remove(last).ok_or_else(not_found),
+        Value::Array(array) => match parse_index(last) {
+            Some(index) if index < array.len() => Ok(array.remove(index)),
+            _ => Err(not_found()),
+        },
+        _ => Err(not_found()),
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: Value =
+            serde_json::from_str(s).map_err(|e| PatchError::Operation(e.to_string()))?;
+        Patch::from_value(&value)
+    }
+}
+
+impl<'de> Deserialize<'de> for Patch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(PatchVisitor)
+    }
+}
+
+struct PatchVisitor;
+
+impl<'de> Visitor<'de> for PatchVisitor {
+    type Value = Patch;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON patch, or a string holding one")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Patch, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Patch, A::Error> {
+        let mut ops = Vec::new();
+        while let Some(value) = seq.next_element::<Value>()? {
+            ops.push(Operation::from_value(&value).map_err(de::Error::custom)?);
+        }
+        Ok(Patch(ops))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn apply(doc: Value, patch: Value) -> Result<Value, PatchError> {
+        let mut doc = doc;
+        Patch::from_value(&patch)?.apply(&mut doc)?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn applies_each_operation() {
+        let doc = json!({"a": {"b": 1}, "c": [1, 2]});
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "add", "path": "/a/d", "value": 2}]),
+        );
+        assert_eq!(
+            patched.unwrap(),
+            json!({"a": {"b": 1, "d": 2}, "c": [1, 2]})
+        );
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "add", "path": "/c/1", "value": 3}]),
+        );
+        assert_eq!(patched.unwrap(), json!({"a": {"b": 1}, "c": [1, 3, 2]}));
+
+        let patched = apply(doc.clone(), json!([{"op": "remove", "path": "/a/b"}]));
+        assert_eq!(patched.unwrap(), json!({"a": {}, "c": [1, 2]}));
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "replace", "path": "/c/0", "value": 0}]),
+        );
+        assert_eq!(patched.unwrap(), json!({"a": {"b": 1}, "c": [0, 2]}));
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "move", "from": "/a/b", "path": "/c/0"}]),
+        );
+        assert_eq!(patched.unwrap(), json!({"a": {}, "c": [1, 1, 2]}));
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "copy", "from": "/c", "path": "/a/c"}]),
+        );
+        assert_eq!(
+            patched.unwrap(),
+            json!({"a": {"b": 1, "c": [1, 2]}, "c": [1, 2]})
+        );
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "test", "path": "/c", "value": [1, 2]}]),
+        );
+        assert_eq!(patched.unwrap(), doc);
+
+        let patched = apply(
+            doc.clone(),
+            json!([{"op": "test", "path": "/c", "value": [2, 1]}]),
+        );
+        assert_eq!(patched, Err(PatchError::Test("/c".to_string())));
+
+        let patched = apply(doc, json!([{"op": "move", "from": "/a", "path": "/a/b"}]));
+        assert!(matches!(patched, Err(PatchError::Operation(_))));
+    }
+
+    #[test]
+    fn tests_numbers_by_value() {
+        let doc = json!({"b": {"c": 1, "d": [2.5, {"e": -3}]}});
+        let patch = json!([
+            {"op": "test", "path": "/b/c", "value": 1.0},
+            {"op": "test", "path": "/b", "value": {"d": [2.5, {"e": -3.0}], "c": 1}},
+        ]);
+        assert_eq!(apply(doc.clone(), patch).unwrap(), doc);
+
+        let patch = json!([{"op": "test", "path": "/b/c", "value": 1.5}]);
+        assert!(apply(doc.clone(), patch).is_err());
+        let patch = json!([{"op": "test", "path": "/b/c", "value": "1"}]);
+        assert!(apply(doc.clone(), patch).is_err());
+        let patch = json!([{"op": "test", "path": "/b", "value": {"c": 1}}]);
+        assert!(apply(doc, patch).is_err());
+
+        let doc = json!([u64::MAX, i64::MIN]);
+        let patch = json!([
+            {"op": "test", "path": "/0", "value": u64::MAX},
+            {"op": "test", "path": "/1", "value": i64::MIN},
+        ]);
+        assert!(apply(doc.clone(), patch).is_ok());
+        let patch = json!([{"op": "test", "path": "/0", "value": u64::MAX - 1}]);
+        assert!(apply(doc, patch).is_err());
+    }
+
+    #[test]
+    fn appends_to_arrays() {
+        let patch = json!([
+            {"op": "add", "path": "/-", "value": 3},
+            {"op": "add", "path": "/-", "value": [4]},
+        ]);
+        assert_eq!(apply(json!([1, 2]), patch).unwrap(), json!([1, 2, 3, [4]]));
+
+        let patch = json!([{"op": "remove", "path": "/-"}]);
+        assert_eq!(
+            apply(json!([1]), patch),
+            Err(PatchError::Path("/-".to_string()))
+        );
+    }
+
+    #[test]
+    fn unescapes_paths() {
+        let doc = json!({"a/b": 1, "m~n": 2});
+        let patch = json!([
+            {"op": "replace", "path": "/a~1b", "value": 3},
+            {"op": "move", "from": "/m~0n", "path": "/~01"},
+        ]);
+        assert_eq!(apply(doc, patch).unwrap(), json!({"a/b": 3, "~1": 2}));
+    }
+
+    #[test]
+    fn rejects_out_of_range_indices() {
+        let doc = json!({"a": [1, 2]});
+        for patch in [
+            json!([{"op": "add", "path": "/a/3", "value": 0}]),
+            json!([{"op": "add", "path": "/a/01", "value": 0}]),
+            json!([{"op": "remove", "path": "/a/2"}]),
+            json!([{"op": "replace", "path": "/a/2", "value": 0}]),
+            json!([{"op": "copy", "from": "/a/2", "path": "/b"}]),
+            json!([{"op": "remove", "path": "/b/0"}]),
+        ] {
+            assert!(matches!(
+                apply(doc.clone(), patch),
+                Err(PatchError::Path(_))
+            ));
+        }
+
+        let patch = json!([{"op": "add", "path": "/a/2", "value": 3}]);
+        assert_eq!(apply(doc, patch).unwrap(), json!({"a": [1, 2, 3]}));
+    }
+
+    #[test]
+    fn rolls_back_on_failure() {
+        let mut doc = json!({"a": 1});
+        let patch: Patch = r#"[
+            {"op": "add", "path": "/b", "value": 2},
+            {"op": "remove", "path": "/a"},
+            {"op": "test", "path": "/b", "value": 3}
+        ]"#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            patch.apply(&mut doc),
+            Err(PatchError::Test("/b".to_string()))
+        );
+        assert_eq!(doc, json!({"a": 1}));
+    }
+
+    #[test]
+    fn rejects_malformed_patches() {
+        for patch in [
+            json!({"op": "add", "path": "/a", "value": 1}),
+            json!([{"op": "add", "path": "/a"}]),
+            json!([{"op": "delete", "path": "/a"}]),
+            json!([{"op": "remove", "path": "a"}]),
+            json!([{"op": "remove", "path": ""}]),
+        ] {
+            assert!(apply(json!({"a": 1}), patch).is_err());
+        }
+    }
+
+    #[test]
+    fn deserializes_from_a_string() {
+        let ops = json!([{"op": "remove", "path": "/a"}]);
+        let from_seq: Patch = serde_json::from_value(ops.clone()).unwrap();
+        let from_str: Patch = serde_json::from_value(Value::String(ops.to_string())).unwrap();
+        assert_eq!(from_seq, from_str);
+        assert_eq!(
+            from_seq,
+            Patch(vec![Operation::Remove {
+                path: Pointer::parse("/a").unwrap()
+            }])
+        );
+    }
+}
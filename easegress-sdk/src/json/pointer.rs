@@ -0,0 +1,187 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde_json::Value;
+
+use super::PatchError;
+
+/// `Pointer` is a JSON Pointer, see RFC 6901.
+///
+/// ```ignore
+/// let pointer: Pointer = "/users/0/name".parse()?;
+/// let name = pointer.get(&doc);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pointer {
+    tokens: Vec<String>,
+}
+
+impl Pointer {
+    /// Return the pointer to the whole document.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parse a pointer in its string form, e.g. `/a~1b/0`.
+    pub fn parse(str: &str) -> Result<Self, PatchError> {
+        if str.is_empty() {
+            return Ok(Self::root());
+        }
+        let Some(rest) = str.strip_prefix('/') else {
+            return Err(PatchError::Pointer(str.to_string()));
+        };
+
+        let mut tokens = Vec::new();
+        for raw in rest.split('/') {
+            let mut token = String::with_capacity(raw.len());
+            let mut chars = raw.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    token.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => token.push('~'),
+                    Some('1') => token.push('/'),
+                    _ => return Err(PatchError::Pointer(str.to_string())),
+                }
+            }
+            tokens.push(token);
+        }
+        Ok(Self { tokens })
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Return the unescaped reference tokens.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Return the pointer to the parent and the last token, or `None` for the root.
+    pub fn split_last(&self) -> Option<(Pointer, &str)> {
+        let (last, parent) = self.tokens.split_last()?;
+        let parent = Pointer {
+            tokens: parent.to_vec(),
+        };
+        Some((parent, last))
+    }
+
+    /// Report whether `self` is a proper prefix of `other`.
+    pub fn is_proper_prefix_of(&self, other: &Pointer) -> bool {
+        self.tokens.len() < other.tokens.len() && other.tokens.starts_with(&self.tokens)
+    }
+
+    /// Return the value `self` refers to in `doc`.
+    pub fn get<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        let mut value = doc;
+        for token in self.tokens.iter() {
+            value = match value {
+                Value::Object(map) => map.get(token)?,
+                Value::Array(array) => array.get(parse_index(token)?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Return the value `self` refers to in `doc` mutably.
+    pub fn get_mut<'a>(&self, doc: &'a mut Value) -> Option<&'a mut Value> {
+        let mut value = doc;
+        for token in self.tokens.iter() {
+            value = match value {
+                Value::Object(map) => map.get_mut(token)?,
+                Value::Array(array) => array.get_mut(parse_index(token)?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// Parse an array index, which is `0` or a number without leading zeros.
+pub(super) fn parse_index(token: &str) -> Option<usize> {
+    let valid = !token.is_empty()
+        && token.bytes().all(|b| b.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    if valid {
+        token.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl FromStr for Pointer {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pointer::parse(s)
+    }
+}
+
+impl fmt::Display for Pointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens.iter() {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pointer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let str = String::deserialize(deserializer)?;
+        Pointer::parse(&str).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_and_escapes_tokens() {
+        let pointer = Pointer::parse("/a~1b/~0c/~01/").unwrap();
+        assert_eq!(pointer.tokens(), ["a/b", "~c", "~1", ""]);
+        assert_eq!(pointer.to_string(), "/a~1b/~0c/~01/");
+
+        assert!(Pointer::parse("").unwrap().is_root());
+        assert_eq!(Pointer::parse("/").unwrap().tokens(), [""]);
+        for invalid in ["a", "/~", "/~2", "/a~"] {
+            assert_eq!(
+                Pointer::parse(invalid),
+                Err(PatchError::Pointer(invalid.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn gets_values() {
+        let doc = json!({"a/b": [1, {"": 2}], "m~n": 3});
+        let get = |p: &str| Pointer::parse(p).unwrap().get(&doc).cloned();
+        assert_eq!(get(""), Some(doc.clone()));
+        assert_eq!(get("/a~1b/0"), Some(json!(1)));
+        assert_eq!(get("/a~1b/1/"), Some(json!(2)));
+        assert_eq!(get("/m~0n"), Some(json!(3)));
+        assert_eq!(get("/a~1b/2"), None);
+        assert_eq!(get("/a~1b/-"), None);
+        assert_eq!(get("/a~1b/01"), None);
+        assert_eq!(get("/m~0n/0"), None);
+    }
+
+    #[test]
+    fn checks_prefixes() {
+        let a = Pointer::parse("/a").unwrap();
+        assert!(a.is_proper_prefix_of(&Pointer::parse("/a/b").unwrap()));
+        assert!(!a.is_proper_prefix_of(&a));
+        assert!(!a.is_proper_prefix_of(&Pointer::parse("/ab").unwrap()));
+        assert!(Pointer::root().is_proper_prefix_of(&a));
+    }
+}
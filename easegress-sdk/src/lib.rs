@@ -2,24 +2,44 @@
 
 use std::collections::HashMap;
 
-use crate::marshal::marshal_string;
+use crate::host::with_host;
 
 extern crate wee_alloc;
 
+#[cfg(target_arch = "wasm32")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 pub mod cluster;
+#[cfg(feature = "compression")]
+pub mod content_encoding;
 pub mod cookie;
+pub mod date;
+pub mod header;
+pub mod host;
+#[cfg(feature = "http")]
+pub mod http_compat;
+#[cfg(feature = "serde_json")]
+pub mod json;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
 mod marshal;
+pub mod method;
+#[cfg(feature = "serde")]
+pub mod params;
 pub mod request;
 pub mod response;
+pub mod router;
+pub mod status;
 
 /// wasm_alloc is an export function for Easegress. Do not use it.
 #[no_mangle]
 pub extern "C" fn wasm_alloc(size: i32) -> i32 {
-    let buf: Vec<u8> = Vec::with_capacity(size as usize);
-    buf.as_ptr() as i32
+    let mut buf: Vec<u8> = Vec::with_capacity(size as usize);
+    let ptr = buf.as_mut_ptr();
+    // the buffer is owned by the caller from now on, it is released by `wasm_free`, or by the
+    // SDK once it has read a buffer the host returned.
+    std::mem::forget(buf);
+    ptr as i32
 }
 
 /// wasm_free is an export function for Easegress. Do not use it.
@@ -31,6 +51,15 @@ pub extern "C" fn wasm_free(ptr: i32) {
     drop(data);
 }
 
+/// Decode and release the parameters passed to `wasm_init`. It is used by `#[easegress_object]`.
+#[doc(hidden)]
+pub fn unmarshal_params(ptr: i32) -> HashMap<String, String> {
+    marshal::unmarshal_string_vec(ptr)
+        .chunks_exact(2)
+        .map(|kv| (kv[0].clone(), kv[1].clone()))
+        .collect()
+}
+
 /// Extend the ability of Easegress by implement `Program` trait.
 pub trait Program {
     /// Easegress will call `new` when initializing the WasmHost filter. You can initialize your struct here.
@@ -49,30 +78,96 @@ pub trait Program {
     /// ```
     ///
     /// `HashMap<String, String>` contains {"blockRation": "0.4", "maxPermission": "3"}.
+    ///
+    /// With the `serde` feature, `#[easegress_object]` also accepts a `new` taking a config struct
+    /// implementing `Deserialize` instead, see the `params` module.
+    ///
+    /// If the parameters may be invalid, implement `try_new` returning `Result<Self, E>` instead, where
+    /// `E` implements `Display`. The error is logged with `log` from `wasm_init`, and `run` is never
+    /// called for the filter.
     fn new(params: HashMap<String, String>) -> Self;
 
     /// Easegress will call `run` on each request.
+    ///
+    /// `#[easegress_object]` also accepts a `run` returning `RunResult`, or any type implementing
+    /// `Into<i32>`.
     fn run(&self) -> i32 {
         0
     }
 }
 
-#[link(wasm_import_module = "easegress")]
-extern "C" {
-    fn host_add_tag(addr: i32);
-    fn host_log(level: i32, msg: i32);
-    fn host_get_unix_time_in_ms() -> i64;
-    fn host_rand() -> f64;
+/// `RunResult` is the value returned by `run`, Easegress maps it to the result of the WasmHost
+/// filter, which can be used in `jumpIf` of the pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum RunResult {
+    /// Continue with the next filter, the filter result is empty.
+    #[default]
+    Continue = 0,
+    Result1 = 1,
+    Result2 = 2,
+    Result3 = 3,
+    Result4 = 4,
+    Result5 = 5,
+    Result6 = 6,
+    Result7 = 7,
+    Result8 = 8,
+    Result9 = 9,
+}
+
+impl RunResult {
+    /// Return the filter result, e.g. `wasmResult1` for `Result1`, or an empty string for
+    /// `Continue`.
+    pub fn filter_result(&self) -> &'static str {
+        match self {
+            RunResult::Continue => "",
+            RunResult::Result1 => "wasmResult1",
+            RunResult::Result2 => "wasmResult2",
+            RunResult::Result3 => "wasmResult3",
+            RunResult::Result4 => "wasmResult4",
+            RunResult::Result5 => "wasmResult5",
+            RunResult::Result6 => "wasmResult6",
+            RunResult::Result7 => "wasmResult7",
+            RunResult::Result8 => "wasmResult8",
+            RunResult::Result9 => "wasmResult9",
+        }
+    }
+}
+
+impl From<RunResult> for i32 {
+    fn from(result: RunResult) -> Self {
+        result as i32
+    }
+}
+
+impl TryFrom<i32> for RunResult {
+    type Error = i32;
+
+    /// Values out of `0..=9` are returned as the error, Easegress maps them to `wasmError`.
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        let result = match value {
+            0 => RunResult::Continue,
+            1 => RunResult::Result1,
+            2 => RunResult::Result2,
+            3 => RunResult::Result3,
+            4 => RunResult::Result4,
+            5 => RunResult::Result5,
+            6 => RunResult::Result6,
+            7 => RunResult::Result7,
+            8 => RunResult::Result8,
+            9 => RunResult::Result9,
+            _ => return Err(value),
+        };
+        Ok(result)
+    }
 }
 
 /// AddTag add a tag to the Request Context.
 #[no_mangle]
 pub fn add_tag(tag: String) {
-    let data = marshal_string(tag);
-    unsafe { host_add_tag(data.as_ptr() as i32) }
+    with_host(|h| h.add_tag(&tag))
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum LogLevel {
     Debug = 0,
     Info = 1,
@@ -83,19 +178,35 @@ pub enum LogLevel {
 /// print log in Easegress server.
 #[no_mangle]
 pub fn log(level: LogLevel, msg: String) {
-    let data = marshal_string(msg);
-    unsafe {
-        host_log(level as i32, data.as_ptr() as i32);
-    }
-    drop(data);
+    with_host(|h| h.log(level, &msg))
 }
 
 #[no_mangle]
 pub fn get_unix_time_in_ms() -> i64 {
-    unsafe { host_get_unix_time_in_ms() }
+    with_host(|h| h.get_unix_time_in_ms())
 }
 
 #[no_mangle]
 pub fn rand() -> f64 {
-    unsafe { host_rand() }
+    with_host(|h| h.rand())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_result_round_trips() {
+        for value in 0..=9 {
+            let result = RunResult::try_from(value).unwrap();
+            assert_eq!(i32::from(result), value);
+        }
+        assert_eq!(RunResult::try_from(0), Ok(RunResult::Continue));
+        assert_eq!(RunResult::try_from(9), Ok(RunResult::Result9));
+        assert_eq!(RunResult::try_from(10), Err(10));
+        assert_eq!(RunResult::try_from(-1), Err(-1));
+        assert_eq!(RunResult::default(), RunResult::Continue);
+        assert_eq!(RunResult::Continue.filter_result(), "");
+        assert_eq!(RunResult::Result3.filter_result(), "wasmResult3");
+    }
 }
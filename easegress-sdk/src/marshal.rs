@@ -1,9 +1,9 @@
 // Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
 
-use crate::cookie::Cookie;
-use std::collections::HashMap;
+use crate::header::HeaderMap;
 
 /// marshal Vec<u8>
+/// ```text
 /// -------------------------------
 /// | vec ...
 /// -------------------------------
@@ -11,7 +11,8 @@ use std::collections::HashMap;
 /// -------------------------------
 /// | vec len (4 bytes) | vec ...
 /// -------------------------------
-pub fn marshal_data(data: Vec<u8>) -> Vec<u8> {
+/// ```
+pub fn marshal_data(data: &[u8]) -> Vec<u8> {
     let mut buf: Vec<u8> = Vec::with_capacity(data.len() + 4);
     let length = data.len() as i32;
     buf.extend(length.to_le_bytes());
@@ -29,11 +30,14 @@ pub fn marshal_data(data: Vec<u8>) -> Vec<u8> {
 /// -------------------------------
 /// | vec ...
 /// -------------------------------
+///
+/// The buffer was allocated by the host with `wasm_alloc`, it is released after being read.
 pub fn unmarshal_data(ptr: i32) -> Vec<u8> {
     let p = ptr as *const i32;
     let length = unsafe { std::ptr::read(p) };
     let data =
         unsafe { std::slice::from_raw_parts((ptr + 4) as *const u8, length as usize) }.to_vec();
+    free(ptr, length as usize + 4);
     data
 }
 
@@ -41,7 +45,7 @@ pub fn unmarshal_data(ptr: i32) -> Vec<u8> {
 /// -----------------------------------------
 /// | string len (4 bytes) | string ... | 0 |
 /// -----------------------------------------
-pub fn marshal_string(data: String) -> Vec<u8> {
+pub fn marshal_string(data: &str) -> Vec<u8> {
     let len = data.len() as i32 + 1;
     let mut buf: Vec<u8> = Vec::with_capacity(data.len() + 5);
     buf.extend(len.to_le_bytes());
@@ -59,17 +63,23 @@ pub fn marshal_string(data: String) -> Vec<u8> {
 /// -------------------------------------
 /// | string ...
 /// -------------------------------------
+///
+/// The buffer was allocated by the host with `wasm_alloc`, it is released after being read.
 pub fn unmarshal_string(ptr: i32) -> String {
     let p = ptr as *const i32;
     let length = unsafe { std::ptr::read(p) };
     let data = unsafe { std::slice::from_raw_parts((ptr + 4) as *const u8, (length - 1) as usize) };
-    String::from_utf8_lossy(data).to_string()
+    let str = String::from_utf8_lossy(data).to_string();
+    free(ptr, length as usize + 4);
+    str
 }
 
 /// unmarshal string vec
 /// --------------------------------------------------------------------------------------
 /// | vec len (4 bytes) | string len (4 bytes) | string ... | 0 | string len (4 bytes) ...
 /// --------------------------------------------------------------------------------------
+///
+/// The buffer was allocated by the host with `wasm_alloc`, it is released after being read.
 pub fn unmarshal_string_vec(ptr: i32) -> Vec<String> {
     let p = ptr as *const i32;
     let length = unsafe { std::ptr::read(p) };
@@ -82,50 +92,135 @@ pub fn unmarshal_string_vec(ptr: i32) -> Vec<String> {
         offset += len;
         data.push(String::from_utf8_lossy(slice).to_string());
     }
+    free(ptr, (offset - ptr) as usize);
     data
 }
 
-pub fn marshal_all_header(headers: HashMap<String, Vec<String>>) -> Vec<u8> {
+/// Release a buffer of `size` bytes allocated by `wasm_alloc`.
+fn free(ptr: i32, size: usize) {
+    // the capacity is what `wasm_alloc` passed to `Vec::with_capacity`
+    drop(unsafe { Vec::from_raw_parts(ptr as *mut u8, 0, size) });
+}
+
+/// marshal headers to a header block
+/// -------------------------------------------
+/// | name: value\r\n | name: value\r\n | ...
+/// -------------------------------------------
+pub fn marshal_all_header(headers: &HeaderMap) -> String {
     let mut str = "".to_string();
     for (key, val) in headers.iter() {
-        for v in val.iter() {
-            str += format!("{}:{}\r\n", *key, *v).as_str();
-        }
+        str += format!("{}: {}\r\n", key, val).as_str();
     }
-    return marshal_string(str);
+    str
 }
 
-pub fn unmarshal_all_header(ptr: i32) -> HashMap<String, Vec<String>> {
-    let str = unmarshal_string(ptr);
-    let headers: Vec<_> = str.split("\r\n").collect();
-    let mut result = HashMap::<String, Vec<String>>::new();
+/// unmarshal headers from a header block, see `marshal_all_header`
+///
+/// Each line is split on its first colon and optional whitespace around the value is trimmed, see
+/// RFC 9110 section 5.5. Lines starting with whitespace continue the previous value (obs-fold,
+/// RFC 9112 section 5.2) and are joined with a single space. Lines without a colon or with an
+/// empty name are skipped. Both `\r\n` and `\n` are accepted as line endings.
+pub fn unmarshal_all_header(str: &str) -> HeaderMap {
+    let is_ows = |c: char| c == ' ' || c == '\t';
+    let mut result = HeaderMap::new();
+    let mut current: Option<(String, String)> = None;
 
-    for header in headers {
-        let kv: Vec<_> = header.split(":").collect();
-        if kv.len() != 2 {
+    for line in str.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.starts_with(is_ows) {
+            if let Some((_, value)) = current.as_mut() {
+                let folded = line.trim_matches(is_ows);
+                if !folded.is_empty() {
+                    if !value.is_empty() {
+                        value.push(' ');
+                    }
+                    value.push_str(folded);
+                }
+            }
             continue;
         }
-        if result.contains_key(kv[0]) {
-            let v = result.get_mut(kv[0]).unwrap();
-            v.push(kv[1].to_string().to_owned());
-        } else {
-            let mut v = Vec::<String>::new();
-            v.push(kv[1].to_string().to_owned());
-            result.insert(kv[0].to_string().to_owned(), v.to_owned());
+
+        if let Some((name, value)) = current.take() {
+            result.append_unchecked(name, value);
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.is_empty() || name.ends_with(is_ows) {
+            continue;
         }
+        current = Some((name.to_string(), value.trim_matches(is_ows).to_string()));
+    }
+
+    if let Some((name, value)) = current {
+        result.append_unchecked(name, value);
     }
     result
 }
 
-pub fn marshal_cookie(c: Cookie) -> Vec<u8> {
-    let str = c.marshal();
-    marshal_string(str)
-}
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn header() -> impl Strategy<Value = (String, String)> {
+        // a token name, and a value without surrounding whitespace which may contain colons
+        (
+            "[A-Za-z0-9!#$%&'*+.^_`|~-]{1,16}",
+            "([!-~é]([ \t!-~é]{0,24}[!-~é])?)?",
+        )
+    }
+
+    fn header_map(headers: &[(String, String)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(name.as_str(), value.as_str()).unwrap();
+        }
+        map
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(headers in prop::collection::vec(header(), 0..8)) {
+            let map = header_map(&headers);
+            prop_assert_eq!(unmarshal_all_header(&marshal_all_header(&map)), map);
+        }
+
+        #[test]
+        fn trims_optional_whitespace(
+            headers in prop::collection::vec((header(), "[ \t]{0,3}", "[ \t]{0,3}"), 0..8),
+            crlf in any::<bool>(),
+        ) {
+            let eol = if crlf { "\r\n" } else { "\n" };
+            let block: String = headers
+                .iter()
+                .map(|((name, value), before, after)| format!("{}:{}{}{}{}", name, before, value, after, eol))
+                .collect();
+            let headers: Vec<_> = headers.into_iter().map(|(h, _, _)| h).collect();
+            prop_assert_eq!(unmarshal_all_header(&block), header_map(&headers));
+        }
+    }
+
+    #[test]
+    fn splits_on_first_colon() {
+        let map = unmarshal_all_header("Location: http://a:80/b\r\nX-Empty:\r\nX-Colon::\r\n");
+        let headers: Vec<_> = map.iter().collect();
+        assert_eq!(
+            headers,
+            vec![
+                ("Location", "http://a:80/b"),
+                ("X-Empty", ""),
+                ("X-Colon", ":")
+            ]
+        );
+    }
 
-pub fn unmarshal_cookie(ptr: i32) -> Option<Cookie> {
-    let str = unmarshal_string(ptr);
-    if str == "" {
-        return None;
+    #[test]
+    fn skips_invalid_lines_and_unfolds() {
+        let map =
+            unmarshal_all_header("no colon\r\n: no name\r\nBad : x\r\nA: 1\r\n  2\r\n\t3\r\n");
+        let headers: Vec<_> = map.iter().collect();
+        assert_eq!(headers, vec![("A", "1 2 3")]);
     }
-    Cookie::unmarshal(str)
 }
@@ -0,0 +1,133 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::header::is_tchar;
+
+/// `Method` is an HTTP request method, see RFC 9110 section 9.
+///
+/// Methods are case-sensitive, so `"get"` parses to an extension method. Extension methods are
+/// created with `parse`, which returns the standard methods as their own variants, so two equal
+/// method strings are always equal `Method`s.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    Extension(ExtensionMethod),
+}
+
+/// `ExtensionMethod` is a method not defined by RFC 9110, e.g. `PROPFIND` of WebDAV. It is a token
+/// other than the standard methods, see `Method::from_str`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExtensionMethod(String);
+
+impl ExtensionMethod {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Error returned when parsing a method which is not a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidMethod(pub String);
+
+impl fmt::Display for InvalidMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid method {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidMethod {}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+            Method::Extension(method) => method.as_str(),
+        }
+    }
+
+    /// Parse a method received from Easegress or `http`, which only pass on tokens.
+    pub(crate) fn from_trusted(method: &str) -> Self {
+        method
+            .parse()
+            .unwrap_or_else(|_| Method::Extension(ExtensionMethod(method.to_string())))
+    }
+}
+
+impl FromStr for Method {
+    type Err = InvalidMethod;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let method = match s {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "CONNECT" => Method::Connect,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "PATCH" => Method::Patch,
+            _ if !s.is_empty() && s.bytes().all(is_tchar) => {
+                Method::Extension(ExtensionMethod(s.to_string()))
+            }
+            _ => return Err(InvalidMethod(s.to_string())),
+        };
+        Ok(method)
+    }
+}
+
+impl AsRef<str> for Method {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_standard_and_extension_methods() {
+        assert_eq!("GET".parse(), Ok(Method::Get));
+        assert_eq!("PATCH".parse(), Ok(Method::Patch));
+
+        let method: Method = "PROPFIND".parse().unwrap();
+        assert!(matches!(&method, Method::Extension(m) if m.as_str() == "PROPFIND"));
+        assert_eq!(method.to_string(), "PROPFIND");
+
+        // methods are case-sensitive
+        let method: Method = "get".parse().unwrap();
+        assert_ne!(method, Method::Get);
+        assert_eq!(method.as_str(), "get");
+
+        assert_eq!(
+            "GET /".parse::<Method>(),
+            Err(InvalidMethod("GET /".to_string()))
+        );
+        assert!("".parse::<Method>().is_err());
+    }
+}
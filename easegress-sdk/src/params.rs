@@ -0,0 +1,490 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Decode the `parameters` of the WasmHost spec into a typed config.
+//!
+//! All parameters are strings in the spec, so values are coerced into the type of the target field:
+//! numbers and booleans are parsed, an empty value becomes `None` for an `Option`, and a comma
+//! separated value becomes a sequence. Durations use the Go syntax of Easegress, e.g. `1m30s`, with
+//! `#[serde(with = "easegress_sdk::params::duration")]`, or `params::duration::option` for an
+//! `Option<Duration>`.
+//!
+//! # Examples
+//! ```ignore
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "camelCase")]
+//! struct Config {
+//!     block_ratio: f64,
+//!     max_permission: u32,
+//!     #[serde(with = "easegress_sdk::params::duration")]
+//!     timeout: Duration,
+//! }
+//!
+//! #[easegress_object]
+//! impl Program for RateLimiter {
+//!     fn new(config: Config) -> Self {
+//!         Self { config }
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+
+/// Error returned when the parameters cannot be decoded into the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self {
+            msg: msg.to_string(),
+        }
+    }
+}
+
+/// Decode `params` into `T`.
+pub fn from_params<T: DeserializeOwned>(params: &HashMap<String, String>) -> Result<T, Error> {
+    T::deserialize(ParamsDeserializer { params })
+}
+
+struct ParamsDeserializer<'a> {
+    params: &'a HashMap<String, String>,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ParamsDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(ParamsAccess {
+            iter: self.params.iter(),
+            key: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct ParamsAccess<'a> {
+    iter: std::collections::hash_map::Iter<'a, String, String>,
+    key: Option<(&'a str, &'a str)>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for ParamsAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.key = Some((k, v));
+                seed.deserialize(k.as_str().into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .key
+            .take()
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|e| Error {
+                msg: format!("invalid value {:?} for `{}`: {}", value, key, e),
+            })
+    }
+}
+
+/// Deserialize a single parameter value, coercing the string into the requested type.
+struct ValueDeserializer<'a>(&'a str);
+
+impl<'a> ValueDeserializer<'a> {
+    fn parse<T>(&self) -> Result<T, Error>
+    where
+        T: std::str::FromStr,
+        T::Err: fmt::Display,
+    {
+        self.0.trim().parse().map_err(de::Error::custom)
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // the same values as `strconv.ParseBool` in Go
+        match self.0.trim() {
+            "1" | "t" | "T" | "true" | "TRUE" | "True" => visitor.visit_bool(true),
+            "0" | "f" | "F" | "false" | "FALSE" | "False" => visitor.visit_bool(false),
+            _ => Err(de::Error::custom("expected a boolean")),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.trim().is_empty() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let items: Vec<_> = if self.0.trim().is_empty() {
+            Vec::new()
+        } else {
+            self.0
+                .split(',')
+                .map(|s| ValueDeserializer(s.trim()))
+                .collect()
+        };
+        visitor.visit_seq(de::value::SeqDeserializer::new(items.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self.0.trim().into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct tuple_struct map struct identifier
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, Error> for ValueDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Deserialize a `Duration` from a Go duration string, for `#[serde(with = "...")]`.
+pub mod duration {
+    use std::time::Duration;
+
+    use serde::de::{self, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_duration(&s).map_err(de::Error::custom)
+    }
+
+    /// Deserialize an `Option<Duration>`, an empty value is `None`. Add `#[serde(default)]` to
+    /// accept a missing parameter too.
+    pub mod option {
+        use std::time::Duration;
+
+        use serde::de::{self, Deserialize, Deserializer};
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(s) if !s.trim().is_empty() => super::super::parse_duration(&s)
+                    .map(Some)
+                    .map_err(de::Error::custom),
+                _ => Ok(None),
+            }
+        }
+    }
+}
+
+/// Parse a Go duration string such as `300ms`, `1.5h` or `2h45m`.
+///
+/// Valid units are `ns`, `us` (or `µs`), `ms`, `s`, `m` and `h`. Negative durations are rejected.
+pub fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let invalid = || Error {
+        msg: format!("invalid duration {:?}", s),
+    };
+
+    let mut rest = s.trim();
+    if let Some(r) = rest.strip_prefix('+') {
+        rest = r;
+    }
+    if rest == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = 0f64;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let value: f64 = rest[..end].parse().map_err(|_| invalid())?;
+        rest = &rest[end..];
+
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let nanos = match &rest[..end] {
+            "ns" => 1.0,
+            "us" | "µs" | "μs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return Err(invalid()),
+        };
+        rest = &rest[end..];
+        total += value * nanos;
+    }
+
+    if total > u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_nanos(total.round() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Config {
+        #[serde(with = "duration")]
+        timeout: Duration,
+        #[serde(default, with = "duration::option")]
+        idle: Option<Duration>,
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    enum Mode {
+        Allow,
+        Deny,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Typed {
+        ratio: f64,
+        max_permission: u32,
+        offset: i8,
+        enabled: bool,
+        #[serde(default)]
+        limit: Option<u32>,
+        #[serde(default)]
+        hosts: Vec<String>,
+        #[serde(default)]
+        ports: Vec<u16>,
+        #[serde(default)]
+        pair: Option<(u8, char)>,
+        mode: Mode,
+        name: String,
+    }
+
+    fn typed(extra: &[(&str, &str)]) -> Result<Typed, Error> {
+        let mut p = params(&[
+            ("ratio", "0.5"),
+            ("maxPermission", " 10 "),
+            ("offset", "-3"),
+            ("enabled", "T"),
+            ("mode", "deny"),
+            ("name", " raw "),
+        ]);
+        p.extend(params(extra));
+        from_params(&p)
+    }
+
+    #[test]
+    fn coerces_values() {
+        let config = typed(&[
+            ("hosts", "a.local, b.local ,c.local"),
+            ("ports", "80,443"),
+            ("pair", "1,x"),
+            ("unknown", "ignored"),
+        ])
+        .unwrap();
+        assert_eq!(
+            config,
+            Typed {
+                ratio: 0.5,
+                max_permission: 10,
+                offset: -3,
+                enabled: true,
+                limit: None,
+                hosts: vec!["a.local".into(), "b.local".into(), "c.local".into()],
+                ports: vec![80, 443],
+                pair: Some((1, 'x')),
+                mode: Mode::Deny,
+                // strings are kept as is
+                name: " raw ".to_string(),
+            }
+        );
+
+        for value in ["0", "f", "F", "false", "FALSE", "False"] {
+            assert!(!typed(&[("enabled", value)]).unwrap().enabled);
+        }
+        assert_eq!(typed(&[("limit", "5")]).unwrap().limit, Some(5));
+        assert_eq!(typed(&[("mode", "allow")]).unwrap().mode, Mode::Allow);
+        assert!(typed(&[("hosts", " ")]).unwrap().hosts.is_empty());
+    }
+
+    #[test]
+    fn blank_optional_value_is_none() {
+        for value in ["", " ", "\t "] {
+            assert_eq!(typed(&[("limit", value)]).unwrap().limit, None);
+            assert_eq!(typed(&[("pair", value)]).unwrap().pair, None);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        let err = |extra: &[(&str, &str)]| typed(extra).unwrap_err().to_string();
+        assert_eq!(
+            err(&[("maxPermission", "-1")]),
+            r#"invalid value "-1" for `maxPermission`: invalid digit found in string"#
+        );
+        assert_eq!(
+            err(&[("offset", "200")]),
+            r#"invalid value "200" for `offset`: number too large to fit in target type"#
+        );
+        assert_eq!(
+            err(&[("enabled", "yes")]),
+            r#"invalid value "yes" for `enabled`: expected a boolean"#
+        );
+        assert_eq!(
+            err(&[("ratio", "half")]),
+            r#"invalid value "half" for `ratio`: invalid float literal"#
+        );
+        assert_eq!(
+            err(&[("ports", "80,x")]),
+            r#"invalid value "80,x" for `ports`: invalid digit found in string"#
+        );
+        assert_eq!(
+            err(&[("mode", "block")]),
+            r#"invalid value "block" for `mode`: unknown variant `block`, expected `allow` or `deny`"#
+        );
+
+        let err = from_params::<Typed>(&params(&[("ratio", "1")])).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `maxPermission`");
+
+        let err = from_params::<Config>(&params(&[("timeout", "1d")])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"invalid value "1d" for `timeout`: invalid duration "1d""#
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        for (str, expected) in [
+            ("0", Duration::ZERO),
+            ("300ms", Duration::from_millis(300)),
+            ("1.5h", Duration::from_secs(5400)),
+            ("2h45m", Duration::from_secs(9900)),
+            ("+1s500ms", Duration::from_millis(1500)),
+            ("1us", Duration::from_micros(1)),
+            ("1µs", Duration::from_micros(1)),
+            ("10ns", Duration::from_nanos(10)),
+        ] {
+            assert_eq!(parse_duration(str), Ok(expected), "{}", str);
+        }
+        for str in ["", "1", "-1s", "1x", "s", "1.2.3s"] {
+            assert!(parse_duration(str).is_err(), "{}", str);
+        }
+    }
+
+    #[test]
+    fn optional_duration() {
+        let config: Config = from_params(&params(&[("timeout", "1m30s")])).unwrap();
+        assert_eq!(config.timeout, Duration::from_secs(90));
+        assert_eq!(config.idle, None);
+
+        let config: Config = from_params(&params(&[("timeout", "1s"), ("idle", "")])).unwrap();
+        assert_eq!(config.idle, None);
+
+        let config: Config = from_params(&params(&[("timeout", "1s"), ("idle", "5ms")])).unwrap();
+        assert_eq!(config.idle, Some(Duration::from_millis(5)));
+
+        let err = from_params::<Config>(&params(&[("timeout", "1s"), ("idle", "5")]));
+        assert!(err.is_err());
+    }
+}
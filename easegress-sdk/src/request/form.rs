@@ -0,0 +1,467 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::header::HeaderMap;
+use crate::marshal::unmarshal_all_header;
+
+use super::QueryParams;
+
+/// `Form` is a form body, either `application/x-www-form-urlencoded` or `multipart/form-data`.
+///
+/// ```ignore
+/// if let Ok(Form::Multipart(form)) = request::get_form() {
+///     for part in form.parts().iter().filter(|p| p.filename().is_some()) {
+///         if part.body().len() > MAX_UPLOAD_SIZE {
+///             return response::respond(StatusCode::PAYLOAD_TOO_LARGE).send();
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Form {
+    UrlEncoded(QueryParams),
+    Multipart(Multipart),
+}
+
+/// `Multipart` is a `multipart/form-data` body, see RFC 7578.
+///
+/// A form created by `new` has no boundary until it is sent, `content_type` and `encode` then use
+/// the first of `EasegressFormBoundary0`, `EasegressFormBoundary1`, ... which does not appear in
+/// the parts, so both return the same boundary as long as the parts are not changed in between.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Multipart {
+    boundary: Option<String>,
+    parts: Vec<Part>,
+}
+
+/// `Part` is a part of a `multipart/form-data` body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Part {
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+/// Error returned when the body is not a form or is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormError {
+    /// The Content-Type is not a form media type, it holds the Content-Type.
+    ContentType(String),
+    /// The multipart body is malformed.
+    Malformed(String),
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormError::ContentType(ct) => write!(f, "unexpected Content-Type {:?} for a form", ct),
+            FormError::Malformed(msg) => write!(f, "malformed multipart body: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for FormError {}
+
+impl Form {
+    /// Parse `body` according to `content_type`.
+    pub fn parse(content_type: &str, body: &[u8]) -> Result<Self, FormError> {
+        let (media_type, params) = parse_header_value(content_type);
+        match media_type.to_ascii_lowercase().as_str() {
+            "application/x-www-form-urlencoded" => Ok(Form::UrlEncoded(QueryParams::parse(
+                &String::from_utf8_lossy(body),
+            ))),
+            "multipart/form-data" => {
+                let boundary = find_param(&params, "boundary")
+                    .ok_or_else(|| FormError::Malformed("missing boundary".to_string()))?;
+                Multipart::parse(boundary, body).map(Form::Multipart)
+            }
+            _ => Err(FormError::ContentType(content_type.to_string())),
+        }
+    }
+
+    /// Return the first value of field `name`, for multipart it is the body of a part without
+    /// a filename and must be valid UTF-8.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        match self {
+            Form::UrlEncoded(params) => params.get(name),
+            Form::Multipart(form) => form
+                .parts
+                .iter()
+                .find(|p| p.name().as_deref() == Some(name) && p.filename().is_none())
+                .and_then(|p| p.text()),
+        }
+    }
+
+    /// Return the Content-Type to send the form with.
+    pub fn content_type(&self) -> String {
+        match self {
+            Form::UrlEncoded(_) => "application/x-www-form-urlencoded".to_string(),
+            Form::Multipart(form) => form.content_type(),
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Form::UrlEncoded(params) => params.encode().into_bytes(),
+            Form::Multipart(form) => form.encode(),
+        }
+    }
+}
+
+impl Multipart {
+    /// Create an empty form, whose boundary is chosen when it is encoded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty form with `boundary`, which must not appear in the body of any part.
+    pub fn with_boundary(boundary: impl Into<String>) -> Self {
+        Self {
+            boundary: Some(boundary.into()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Parse a multipart body delimited by `boundary`, the preamble and epilogue are ignored.
+    pub fn parse(boundary: &str, body: &[u8]) -> Result<Self, FormError> {
+        if boundary.is_empty() {
+            return Err(FormError::Malformed("empty boundary".to_string()));
+        }
+        let delimiter = format!("--{}", boundary).into_bytes();
+        let close_delimiter = format!("\r\n--{}", boundary).into_bytes();
+
+        let mut pos = if body.starts_with(&delimiter) {
+            0
+        } else {
+            find(body, &close_delimiter)
+                .map(|i| i + 2)
+                .ok_or_else(|| FormError::Malformed("missing boundary".to_string()))?
+        };
+
+        let mut parts = Vec::new();
+        loop {
+            pos += delimiter.len();
+            let rest = &body[pos..];
+            if rest.starts_with(b"--") {
+                break;
+            }
+            // skip transport padding, then the line break ending the delimiter line
+            let padding = rest
+                .iter()
+                .take_while(|b| **b == b' ' || **b == b'\t')
+                .count();
+            if !rest[padding..].starts_with(b"\r\n") {
+                return Err(FormError::Malformed("bad delimiter line".to_string()));
+            }
+            pos += padding + 2;
+
+            let end = find(&body[pos..], &close_delimiter)
+                .map(|i| pos + i)
+                .ok_or_else(|| FormError::Malformed("missing close delimiter".to_string()))?;
+            parts.push(Part::parse(&body[pos..end])?);
+            pos = end + 2;
+        }
+
+        Ok(Self {
+            boundary: Some(boundary.to_string()),
+            parts,
+        })
+    }
+
+    /// Return the boundary of the form, or the one chosen for its parts if it has none.
+    pub fn boundary(&self) -> Cow<'_, str> {
+        match &self.boundary {
+            Some(boundary) => Cow::Borrowed(boundary),
+            None => Cow::Owned(self.choose_boundary()),
+        }
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    pub fn parts_mut(&mut self) -> &mut Vec<Part> {
+        &mut self.parts
+    }
+
+    /// Return the first part named `name`.
+    pub fn get(&self, name: &str) -> Option<&Part> {
+        self.parts
+            .iter()
+            .find(|p| p.name().as_deref() == Some(name))
+    }
+
+    /// Return all parts named `name` in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Part> + 'a {
+        self.parts
+            .iter()
+            .filter(move |p| p.name().as_deref() == Some(name))
+    }
+
+    pub fn push(&mut self, part: Part) {
+        self.parts.push(part);
+    }
+
+    /// Remove the parts named `name`, returning them.
+    pub fn remove(&mut self, name: &str) -> Vec<Part> {
+        let (removed, kept) = self
+            .parts
+            .drain(..)
+            .partition(|p| p.name().as_deref() == Some(name));
+        self.parts = kept;
+        removed
+    }
+
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", quote(&self.boundary()))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let boundary = self.boundary();
+        let mut buf = Vec::new();
+        for part in self.parts.iter() {
+            buf.extend(format!("--{}\r\n", boundary).as_bytes());
+            for (name, value) in part.headers.iter() {
+                buf.extend(format!("{}: {}\r\n", name, value).as_bytes());
+            }
+            buf.extend(b"\r\n");
+            buf.extend(&part.body);
+            buf.extend(b"\r\n");
+        }
+        buf.extend(format!("--{}--\r\n", boundary).as_bytes());
+        buf
+    }
+
+    fn choose_boundary(&self) -> String {
+        (0u64..)
+            .map(|i| format!("EasegressFormBoundary{}", i))
+            .find(|boundary| {
+                let delimiter = format!("--{}", boundary).into_bytes();
+                // header values can not hold the line break starting a delimiter
+                self.parts
+                    .iter()
+                    .all(|part| find(&part.body, &delimiter).is_none())
+            })
+            .expect("a part can not contain every boundary")
+    }
+}
+
+impl Part {
+    /// Create a text field.
+    pub fn field(name: &str, value: impl Into<String>) -> Self {
+        let mut headers = HeaderMap::new();
+        let disposition = format!("form-data; name=\"{}\"", escape_quoted(name));
+        headers.append_unchecked("Content-Disposition".to_string(), disposition);
+        Self {
+            headers,
+            body: value.into().into_bytes(),
+        }
+    }
+
+    /// Create a file field.
+    pub fn file(name: &str, filename: &str, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        let mut headers = HeaderMap::new();
+        let disposition = format!(
+            "form-data; name=\"{}\"; filename=\"{}\"",
+            escape_quoted(name),
+            escape_quoted(filename)
+        );
+        headers.append_unchecked("Content-Disposition".to_string(), disposition);
+        headers.append_unchecked("Content-Type".to_string(), content_type.to_string());
+        Self {
+            headers,
+            body: body.into(),
+        }
+    }
+
+    fn parse(raw: &[u8]) -> Result<Self, FormError> {
+        let (headers, body) = if raw.starts_with(b"\r\n") {
+            (HeaderMap::new(), &raw[2..])
+        } else {
+            let end = find(raw, b"\r\n\r\n")
+                .ok_or_else(|| FormError::Malformed("missing end of part headers".to_string()))?;
+            let headers = unmarshal_all_header(&String::from_utf8_lossy(&raw[..end]));
+            (headers, &raw[end + 4..])
+        };
+        Ok(Self {
+            headers,
+            body: body.to_vec(),
+        })
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Return the `name` parameter of the Content-Disposition header.
+    pub fn name(&self) -> Option<String> {
+        self.disposition_param("name")
+    }
+
+    /// Return the `filename` parameter of the Content-Disposition header, which is set for files.
+    pub fn filename(&self) -> Option<String> {
+        self.disposition_param("filename")
+    }
+
+    /// Return the Content-Type, which defaults to `text/plain` for fields.
+    pub fn content_type(&self) -> &str {
+        self.headers.get("Content-Type").unwrap_or("text/plain")
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Vec<u8>>) {
+        self.body = body.into();
+    }
+
+    /// Return the body as text if it is valid UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.body).ok()
+    }
+
+    fn disposition_param(&self, name: &str) -> Option<String> {
+        let disposition = self.headers.get("Content-Disposition")?;
+        let (_, params) = parse_header_value(disposition);
+        find_param(&params, name).map(|v| v.to_string())
+    }
+}
+
+/// Split a header value like `multipart/form-data; boundary="abc"` into the value and its
+/// parameters. Quoted parameter values are unquoted.
+fn parse_header_value(value: &str) -> (&str, Vec<(String, String)>) {
+    let (head, mut rest) = value.split_once(';').unwrap_or((value, ""));
+    let mut params = Vec::new();
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ';']);
+        if rest.is_empty() {
+            break;
+        }
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        let name = name.trim().to_ascii_lowercase();
+        let after = after.trim_start();
+        let value = if let Some(quoted) = after.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, c)) = chars.next() {
+                            value.push(c);
+                        }
+                    }
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => value.push(c),
+                }
+            }
+            rest = &quoted[end..];
+            value
+        } else {
+            let end = after.find(';').unwrap_or(after.len());
+            rest = &after[end..];
+            after[..end].trim().to_string()
+        };
+        params.push((name, value));
+    }
+    (head.trim(), params)
+}
+
+fn find_param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Quote a parameter value if it is not a token.
+fn quote(value: &str) -> String {
+    if !value.is_empty() && value.bytes().all(crate::header::is_tchar) {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Escape a name or filename like browsers do, see the multipart/form-data encoding algorithm
+/// of the HTML standard.
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::{self, MockHost, MockRequest};
+    use crate::request;
+
+    use super::*;
+
+    #[test]
+    fn chooses_a_boundary_absent_from_the_parts() {
+        // no host is needed to build and encode a form
+        let mut form = Multipart::new();
+        form.push(Part::field(
+            "a",
+            "--EasegressFormBoundary0 and --EasegressFormBoundary1",
+        ));
+        form.push(Part::file("f", "f.txt", "text/plain", "x"));
+        assert_eq!(form.boundary(), "EasegressFormBoundary2");
+        assert_eq!(
+            form.content_type(),
+            "multipart/form-data; boundary=EasegressFormBoundary2"
+        );
+
+        let parsed = Multipart::parse(&form.boundary(), &form.encode()).unwrap();
+        assert_eq!(parsed.parts(), form.parts());
+        assert_eq!(Multipart::default().boundary(), "EasegressFormBoundary0");
+        assert_eq!(Multipart::with_boundary("b").boundary(), "b");
+    }
+
+    #[test]
+    fn set_form_updates_the_body_headers() {
+        let host = MockHost::new(
+            MockRequest::new("POST", "/")
+                .with_header("Content-Encoding", "gzip")
+                .with_header("Content-Length", "1024"),
+        );
+        host::set_host(host.clone());
+
+        let mut form = Multipart::new();
+        form.push(Part::field("name", "value"));
+        let parts = form.parts().to_vec();
+        let form = Form::Multipart(form);
+        request::set_form(&form);
+
+        let req = host.request();
+        let body = form.encode();
+        assert_eq!(req.body, body);
+        assert_eq!(req.get_header("Content-Encoding"), None);
+        assert_eq!(
+            req.get_header("Content-Length"),
+            Some(body.len().to_string().as_str())
+        );
+        match request::get_form() {
+            Ok(Form::Multipart(parsed)) => assert_eq!(parsed.parts(), parts),
+            other => panic!("unexpected form {:?}", other),
+        }
+        host::reset_host();
+    }
+}
@@ -1,194 +1,282 @@
 // Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
 
-use std::collections::HashMap;
-
 use crate::cookie::Cookie;
-use crate::marshal::{
-    marshal_all_header, marshal_cookie, marshal_data, marshal_string, unmarshal_all_header,
-    unmarshal_cookie, unmarshal_data, unmarshal_string, unmarshal_string_vec,
-};
-
-#[link(wasm_import_module = "easegress")]
-extern "C" {
-    fn host_req_get_real_ip() -> i32;
-    fn host_req_get_scheme() -> i32;
-    fn host_req_get_proto() -> i32;
-    fn host_req_get_method() -> i32;
-    fn host_req_set_method(addr: i32);
-    fn host_req_get_host() -> i32;
-    fn host_req_set_host(addr: i32);
-    fn host_req_get_path() -> i32;
-    fn host_req_set_path(addr: i32);
-    fn host_req_get_escaped_path() -> i32;
-    fn host_req_get_query() -> i32;
-    fn host_req_set_query(addr: i32);
-    fn host_req_get_fragment() -> i32;
-    fn host_req_get_header(addr: i32) -> i32;
-    fn host_req_get_all_header() -> i32;
-    fn host_req_set_header(name_addr: i32, value_addr: i32);
-    fn host_req_set_all_header(addr: i32);
-    fn host_req_add_header(name_addr: i32, value_addr: i32);
-    fn host_req_del_header(addr: i32);
-    fn host_req_get_cookie(addr: i32) -> i32;
-    fn host_req_get_all_cookie() -> i32;
-    fn host_req_add_cookie(addr: i32);
-    fn host_req_get_body() -> i32;
-    fn host_req_set_body(addr: i32);
-}
+use crate::header::{self, HeaderMap};
+use crate::host::with_host;
+use crate::marshal::{marshal_all_header, unmarshal_all_header};
+use crate::method::Method;
+
+mod form;
+mod query;
+mod url;
+mod view;
+
+pub use form::{Form, FormError, Multipart, Part};
+pub(crate) use query::percent_decode;
+pub use query::QueryParams;
+pub use url::Url;
+pub use view::Request;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use url::escape_path;
 
 #[no_mangle]
 pub fn get_real_ip() -> String {
-    let ptr = unsafe { host_req_get_real_ip() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_real_ip())
 }
 
 #[no_mangle]
 pub fn get_scheme() -> String {
-    let ptr = unsafe { host_req_get_scheme() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_scheme())
 }
 
 #[no_mangle]
 pub fn get_proto() -> String {
-    let ptr = unsafe { host_req_get_proto() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_proto())
 }
 
 #[no_mangle]
 pub fn get_method() -> String {
-    let ptr = unsafe { host_req_get_method() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_method())
 }
 
 #[no_mangle]
 pub fn set_method(method: String) {
-    let ptr = marshal_string(method);
-    unsafe { host_req_set_method(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_set_method(&method))
+}
+
+/// Return the method as a `Method`, Easegress only accepts requests whose method is a token.
+#[no_mangle]
+pub fn get_typed_method() -> Method {
+    Method::from_trusted(&get_method())
+}
+
+#[no_mangle]
+pub fn set_typed_method(method: Method) {
+    with_host(|h| h.req_set_method(method.as_str()))
 }
 
 #[no_mangle]
 pub fn get_host() -> String {
-    let ptr = unsafe { host_req_get_host() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_host())
 }
 
 #[no_mangle]
 pub fn set_host(host: String) {
-    let ptr = marshal_string(host);
-    unsafe { host_req_set_host(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_set_host(&host))
 }
 
 #[no_mangle]
 pub fn get_path() -> String {
-    let ptr = unsafe { host_req_get_path() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_path())
 }
 
 #[no_mangle]
 pub fn set_path(path: String) {
-    let ptr = marshal_string(path);
-    unsafe { host_req_set_path(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_set_path(&path))
 }
 
 #[no_mangle]
 pub fn get_escape_path() -> String {
-    let ptr = unsafe { host_req_get_escaped_path() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_escaped_path())
 }
 
 #[no_mangle]
 pub fn get_query() -> String {
-    let ptr = unsafe { host_req_get_query() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_query())
 }
 
 #[no_mangle]
 pub fn set_query(query: String) {
-    let ptr = marshal_string(query);
-    unsafe { host_req_set_query(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_set_query(&query))
+}
+
+/// Parse the query string into `QueryParams`.
+#[no_mangle]
+pub fn get_query_params() -> QueryParams {
+    QueryParams::parse(&get_query())
+}
+
+#[no_mangle]
+pub fn set_query_params(params: &QueryParams) {
+    set_query(params.encode())
+}
+
+/// Return the first value of query parameter `name`.
+#[no_mangle]
+pub fn get_query_param(name: String) -> Option<String> {
+    get_query_params().get(&name).map(|v| v.to_string())
+}
+
+/// Set query parameter `name` to `value`, replacing its existing values.
+#[no_mangle]
+pub fn set_query_param(name: String, value: String) {
+    let mut params = get_query_params();
+    params.set(name, value);
+    set_query_params(&params)
+}
+
+/// Remove query parameter `name`, the query is left untouched if it is not present.
+#[no_mangle]
+pub fn remove_query_param(name: String) {
+    let mut params = get_query_params();
+    if !params.remove(&name).is_empty() {
+        set_query_params(&params)
+    }
 }
 
 #[no_mangle]
 pub fn get_fragment() -> String {
-    let ptr = unsafe { host_req_get_fragment() };
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_fragment())
+}
+
+/// Return the URL of the request.
+#[no_mangle]
+pub fn get_url() -> Url {
+    Request::new().url()
 }
 
 #[no_mangle]
 pub fn get_header(name: String) -> String {
-    let ptr = marshal_string(name);
-    let ptr = unsafe { host_req_get_header(ptr.as_ptr() as i32) };
-    drop(ptr);
-    unmarshal_string(ptr)
+    with_host(|h| h.req_get_header(&name))
 }
 
 #[no_mangle]
-pub fn get_all_header() -> HashMap<String, Vec<String>> {
-    let ptr = unsafe { host_req_get_all_header() };
-    unmarshal_all_header(ptr)
+pub fn get_all_header() -> HeaderMap {
+    let headers = with_host(|h| h.req_get_all_header());
+    unmarshal_all_header(&headers)
 }
 
+/// Set header `name`, an invalid name or value is logged and the header is dropped.
 #[no_mangle]
 pub fn set_header(name: String, value: String) {
-    let name_ptr = marshal_string(name);
-    let value_ptr = marshal_string(value);
-    unsafe { host_req_set_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    if header::check_or_log(&name, &value) {
+        with_host(|h| h.req_set_header(&name, &value))
+    }
 }
 
 #[no_mangle]
-pub fn set_all_header(headers: HashMap<String, Vec<String>>) {
-    let ptr = marshal_all_header(headers);
-    unsafe { host_req_set_all_header(ptr.as_ptr() as i32) }
+pub fn set_all_header(headers: &HeaderMap) {
+    let headers = marshal_all_header(headers);
+    with_host(|h| h.req_set_all_header(&headers))
 }
 
+/// Add header `name`, an invalid name or value is logged and the header is dropped.
 #[no_mangle]
 pub fn add_header(name: String, value: String) {
-    let name_ptr = marshal_string(name);
-    let value_ptr = marshal_string(value);
-    unsafe { host_req_add_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    if header::check_or_log(&name, &value) {
+        with_host(|h| h.req_add_header(&name, &value))
+    }
 }
 
 #[no_mangle]
 pub fn del_header(name: String) {
-    let ptr = marshal_string(name);
-    unsafe { host_req_del_header(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_del_header(&name))
 }
 
 #[no_mangle]
 pub fn get_cookie(name: String) -> Option<Cookie> {
-    let ptr = marshal_string(name);
-    let data = unsafe { host_req_get_cookie(ptr.as_ptr() as i32) };
-    unmarshal_cookie(data)
+    let str = with_host(|h| h.req_get_cookie(&name));
+    if str.is_empty() {
+        return None;
+    }
+    Cookie::unmarshal(str)
 }
 
 #[no_mangle]
 pub fn get_all_cookie() -> Vec<Cookie> {
-    let mut result = Vec::<Cookie>::new();
-    let ptr = unsafe { host_req_get_all_cookie() };
-    let strs = unmarshal_string_vec(ptr);
-    for str in strs {
-        let c = Cookie::unmarshal(str);
-        if c.is_some() {
-            result.push(c.unwrap());
-        }
-    }
-    result
+    let strs = with_host(|h| h.req_get_all_cookie());
+    strs.into_iter().filter_map(Cookie::unmarshal).collect()
 }
 
-#[no_mangle]
-pub fn add_cookie(c: Cookie) {
-    let ptr = marshal_cookie(c);
-    unsafe { host_req_add_cookie(ptr.as_ptr() as i32) }
+pub fn add_cookie(c: impl Into<Cookie>) {
+    if let Some(str) = c.into().marshal_or_log() {
+        with_host(|h| h.req_add_cookie(&str))
+    }
 }
 
 #[no_mangle]
 pub fn get_body() -> Vec<u8> {
-    let ptr = unsafe { host_req_get_body() };
-    unmarshal_data(ptr)
+    with_host(|h| h.req_get_body())
 }
 
 #[no_mangle]
 pub fn set_body(body: Vec<u8>) {
-    let ptr = marshal_data(body);
-    unsafe { host_req_set_body(ptr.as_ptr() as i32) }
+    with_host(|h| h.req_set_body(&body))
+}
+
+/// Parse the body as a form according to the Content-Type.
+#[no_mangle]
+pub fn get_form() -> Result<Form, FormError> {
+    let content_type = get_header("Content-Type".to_string());
+    Form::parse(&content_type, &get_body())
+}
+
+/// Set the body to `form`, the Content-Type to the one of `form`, remove the Content-Encoding and
+/// update the Content-Length.
+#[no_mangle]
+pub fn set_form(form: &Form) {
+    let body = form.encode();
+    set_header("Content-Type".to_string(), form.content_type());
+    del_header("Content-Encoding".to_string());
+    set_header("Content-Length".to_string(), body.len().to_string());
+    set_body(body)
+}
+
+/// Decode the body as JSON, the Content-Type must be a JSON media type.
+#[cfg(feature = "serde_json")]
+pub fn get_json<T: serde::de::DeserializeOwned>() -> Result<T, crate::json::JsonError> {
+    let content_type = get_header("Content-Type".to_string());
+    crate::json::decode(&content_type, &get_body())
+}
+
+/// Set the body to `value` encoded as JSON, and the Content-Type to `application/json`.
+#[cfg(feature = "serde_json")]
+pub fn set_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<(), crate::json::JsonError> {
+    let body = crate::json::encode(value)?;
+    set_header(
+        "Content-Type".to_string(),
+        crate::json::CONTENT_TYPE.to_string(),
+    );
+    set_body(body);
+    Ok(())
+}
+
+/// Apply the JSON Patch `patch` to the body, the Content-Type must be a JSON media type and is
+/// kept as is.
+#[cfg(feature = "serde_json")]
+pub fn patch_json(patch: &crate::json::Patch) -> Result<(), crate::json::JsonError> {
+    let content_type = get_header("Content-Type".to_string());
+    let body = crate::json::patch(&content_type, &get_body(), patch)?;
+    set_body(body);
+    Ok(())
+}
+
+/// Return the body decoded according to the Content-Encoding, failing with
+/// `EncodingError::TooLarge` if it exceeds `max_size` bytes.
+#[cfg(feature = "compression")]
+pub fn get_decoded_body(
+    max_size: usize,
+) -> Result<Vec<u8>, crate::content_encoding::EncodingError> {
+    let encoding = get_header("Content-Encoding".to_string());
+    crate::content_encoding::decode(&encoding, &get_body(), max_size)
+}
+
+/// Set the body to `body` encoded according to the Content-Encoding, and update the
+/// Content-Length.
+#[cfg(feature = "compression")]
+pub fn set_decoded_body(body: Vec<u8>) -> Result<(), crate::content_encoding::EncodingError> {
+    let encoding = get_header("Content-Encoding".to_string());
+    let body = crate::content_encoding::encode(&encoding, &body)?;
+    set_header("Content-Length".to_string(), body.len().to_string());
+    set_body(body);
+    Ok(())
+}
+
+/// Set the body to `body` as is, remove the Content-Encoding and update the Content-Length.
+#[cfg(feature = "compression")]
+pub fn set_identity_body(body: Vec<u8>) {
+    del_header("Content-Encoding".to_string());
+    set_header("Content-Length".to_string(), body.len().to_string());
+    set_body(body)
 }
@@ -0,0 +1,294 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+
+/// `QueryParams` is an ordered multi-value map of query parameters.
+///
+/// Parsing and encoding follow `application/x-www-form-urlencoded`, names are case-sensitive.
+///
+/// ```ignore
+/// let mut params = request::get_query_params();
+/// params.set("page", "1");
+/// params.remove("debug");
+/// request::set_query_params(&params);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryParams {
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a query string, with or without the leading `?`.
+    ///
+    /// Empty pairs are skipped, a pair without `=` has an empty value, and malformed percent
+    /// escapes are kept as is.
+    pub fn parse(query: &str) -> Self {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        let pairs = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_component(name), decode_component(value))
+            })
+            .collect();
+        Self { pairs }
+    }
+
+    /// Return the number of pairs.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == name)
+    }
+
+    /// Return the first value of parameter `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Return all values of parameter `name` in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Add a value to parameter `name`, keeping its existing values.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((name.into(), value.into()));
+    }
+
+    /// Set parameter `name` to `value`, replacing its existing values in place.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let (name, value) = (name.into(), value.into());
+        match self.pairs.iter().position(|(k, _)| *k == name) {
+            Some(pos) => {
+                let mut i = 0;
+                self.pairs.retain(|(k, _)| {
+                    let keep = i <= pos || *k != name;
+                    i += 1;
+                    keep
+                });
+                self.pairs[pos].1 = value;
+            }
+            None => self.pairs.push((name, value)),
+        }
+    }
+
+    /// Remove parameter `name`, returning its values.
+    pub fn remove(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.pairs.retain(|(k, v)| {
+            if k == name {
+                removed.push(v.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Iterate over all `(name, value)` pairs in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Encode the parameters into a query string without the leading `?`.
+    pub fn encode(&self) -> String {
+        let mut str = String::new();
+        for (name, value) in self.pairs.iter() {
+            if !str.is_empty() {
+                str.push('&');
+            }
+            encode_component(&mut str, name);
+            str.push('=');
+            encode_component(&mut str, value);
+        }
+        str
+    }
+}
+
+impl fmt::Display for QueryParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for QueryParams {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let pairs = iter
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        Self { pairs }
+    }
+}
+
+impl<'a> IntoIterator for &'a QueryParams {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Box<dyn Iterator<Item = (&'a str, &'a str)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl IntoIterator for QueryParams {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+/// Decode a form-urlencoded component, `+` is decoded as a space.
+pub(crate) fn decode_component(str: &str) -> String {
+    percent_decode(str, true)
+}
+
+/// Decode the percent escapes of `str`, malformed escapes are kept as is.
+pub(crate) fn percent_decode(str: &str, plus_as_space: bool) -> String {
+    let bytes = str.as_bytes();
+    let mut buf = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => buf.push(b' '),
+            b'%' => match bytes.get(i + 1..i + 3).and_then(|h| hex_pair(h[0], h[1])) {
+                Some(b) => {
+                    buf.push(b);
+                    i += 2;
+                }
+                None => buf.push(b'%'),
+            },
+            b => buf.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+/// Encode a form-urlencoded component into `buf`, a space is encoded as `+`.
+pub(crate) fn encode_component(buf: &mut String, str: &str) {
+    for b in str.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'*' | b'-' | b'.' | b'_' => {
+                buf.push(b as char)
+            }
+            b' ' => buf.push('+'),
+            _ => *buf += format!("%{:02X}", b).as_str(),
+        }
+    }
+}
+
+fn hex_pair(hi: u8, lo: u8) -> Option<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    Some(hex(hi)? << 4 | hex(lo)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{reset_host, MockHost, MockRequest};
+    use crate::request;
+
+    fn pairs(params: &QueryParams) -> Vec<(&str, &str)> {
+        params.iter().collect()
+    }
+
+    #[test]
+    fn parses_pairs() {
+        let params = QueryParams::parse("?a=1&&b&c=&a=2&=x");
+        assert_eq!(
+            pairs(&params),
+            vec![("a", "1"), ("b", ""), ("c", ""), ("a", "2"), ("", "x")]
+        );
+        assert!(QueryParams::parse("").is_empty());
+        assert!(QueryParams::parse("?").is_empty());
+    }
+
+    #[test]
+    fn decodes_components() {
+        let params = QueryParams::parse("q=a+b%20c%2B&na%6De=%E4%BD%A0%e5%a5%bd&e=%2&f=%zz%");
+        assert_eq!(params.get("q"), Some("a b c+"));
+        assert_eq!(params.get("name"), Some("你好"));
+        // malformed escapes are kept as is
+        assert_eq!(params.get("e"), Some("%2"));
+        assert_eq!(params.get("f"), Some("%zz%"));
+        // invalid UTF-8 is replaced
+        assert_eq!(QueryParams::parse("x=%FF").get("x"), Some("\u{FFFD}"));
+
+        assert_eq!(percent_decode("a+b%2F", false), "a+b/");
+    }
+
+    #[test]
+    fn encodes_components() {
+        let params: QueryParams = [("a b", "c+d&e=f"), ("g", "你"), ("safe", "*-._~")]
+            .into_iter()
+            .collect();
+        let encoded = params.encode();
+        assert_eq!(encoded, "a+b=c%2Bd%26e%3Df&g=%E4%BD%A0&safe=*-._%7E");
+        assert_eq!(QueryParams::parse(&encoded), params);
+        assert_eq!(QueryParams::new().encode(), "");
+    }
+
+    #[test]
+    fn handles_repeated_keys() {
+        let mut params = QueryParams::parse("a=1&b=2&a=3&c=4&a=5");
+        assert_eq!(params.get("a"), Some("1"));
+        assert_eq!(params.get_all("a").collect::<Vec<_>>(), vec!["1", "3", "5"]);
+
+        params.set("a", "6");
+        assert_eq!(params.encode(), "a=6&b=2&c=4");
+
+        params.append("b", "7");
+        params.set("d", "8");
+        assert_eq!(params.encode(), "a=6&b=2&c=4&b=7&d=8");
+
+        assert_eq!(params.remove("b"), vec!["2".to_string(), "7".to_string()]);
+        assert!(params.remove("b").is_empty());
+        assert!(!params.contains_key("b"));
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn writes_back_to_host() {
+        let host = MockHost::new(MockRequest::new("GET", "/").with_query("a=1&b=x+y&a=2"));
+        host.install();
+
+        assert_eq!(
+            request::get_query_param("b".to_string()),
+            Some("x y".to_string())
+        );
+        assert_eq!(request::get_query_param("c".to_string()), None);
+
+        request::set_query_param("a".to_string(), "3 4".to_string());
+        assert_eq!(host.request().query, "a=3+4&b=x+y");
+
+        request::remove_query_param("b".to_string());
+        assert_eq!(host.request().query, "a=3+4");
+
+        // the query is not re-encoded if the parameter is missing
+        host.set_request(MockRequest::new("GET", "/").with_query("a=%7e"));
+        request::remove_query_param("b".to_string());
+        assert_eq!(host.request().query, "a=%7e");
+        reset_host();
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::fmt;
+
+use super::query::QueryParams;
+use super::view::Request;
+
+/// `Url` is the URL of the request being processed, assembled from its scheme, host, path, query
+/// and fragment.
+///
+/// Modifications are local until `apply` writes the changed host, path and query back to
+/// Easegress. The scheme and fragment can not be changed.
+///
+/// ```ignore
+/// let mut url = request::get_url();
+/// url.normalize();
+/// if url.segments().next() == Some("v1") {
+///     url.set_host("v1.backend.local");
+/// }
+/// url.apply();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    scheme: String,
+    host: String,
+    path: String,
+    query: String,
+    fragment: String,
+    // host, path and query known to Easegress, `None` if the URL is not from the request.
+    applied: Option<(String, String, String)>,
+}
+
+impl Url {
+    pub(super) fn from_request(req: &Request) -> Self {
+        let (host, path, query) = (req.host(), req.path(), req.query());
+        Self {
+            scheme: req.scheme().to_string(),
+            host: host.to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+            fragment: req.fragment().to_string(),
+            applied: Some((host.to_string(), path.to_string(), query.to_string())),
+        }
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn set_host(&mut self, host: impl Into<String>) {
+        self.host = host.into();
+    }
+
+    /// Return the decoded path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn set_path(&mut self, path: impl Into<String>) {
+        self.path = path.into();
+    }
+
+    /// Return the path with the characters not allowed in a path segment percent-encoded.
+    pub fn escaped_path(&self) -> String {
+        escape_path(&self.path)
+    }
+
+    /// Iterate over the segments of the path, `/a/b/` yields `a`, `b` and an empty segment.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        let path = self.path.strip_prefix('/').unwrap_or(&self.path);
+        path.split('/').filter(move |_| !self.path.is_empty())
+    }
+
+    /// Return the raw query string without the leading `?`.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn set_query(&mut self, query: impl Into<String>) {
+        self.query = query.into();
+    }
+
+    pub fn query_params(&self) -> QueryParams {
+        QueryParams::parse(&self.query)
+    }
+
+    pub fn set_query_params(&mut self, params: &QueryParams) {
+        self.query = params.encode();
+    }
+
+    /// Return the decoded fragment.
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+
+    /// Remove the dot segments from the path as described in RFC 3986 section 5.2.4, and
+    /// collapse duplicate slashes.
+    pub fn normalize(&mut self) {
+        self.path = normalize_path(&self.path);
+    }
+
+    /// Write the host, path and query changed since the URL was read or last applied back to
+    /// Easegress. All of them are written if the URL is not from the request.
+    ///
+    /// A `Request` created before this call keeps its cached values.
+    pub fn apply(&mut self) {
+        let all = self.applied.is_none();
+        let (host, path, query) = self.applied.take().unwrap_or_default();
+        if all || host != self.host {
+            super::set_host(self.host.clone());
+        }
+        if all || path != self.path {
+            super::set_path(self.path.clone());
+        }
+        if all || query != self.query {
+            super::set_query(self.query.clone());
+        }
+        self.applied = Some((self.host.clone(), self.path.clone(), self.query.clone()));
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}{}", self.scheme, self.host, self.escaped_path())?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query)?;
+        }
+        if !self.fragment.is_empty() {
+            write!(f, "#{}", escape_path(&self.fragment))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "url")]
+impl TryFrom<&Url> for url::Url {
+    type Error = url::ParseError;
+
+    fn try_from(u: &Url) -> Result<Self, Self::Error> {
+        url::Url::parse(&u.to_string())
+    }
+}
+
+#[cfg(feature = "url")]
+impl From<&url::Url> for Url {
+    /// The result is not tied to the request, so `apply` writes all of host, path and query.
+    fn from(u: &url::Url) -> Self {
+        let mut host = u.host_str().unwrap_or_default().to_string();
+        if let Some(port) = u.port() {
+            host += format!(":{}", port).as_str();
+        }
+        Self {
+            scheme: u.scheme().to_string(),
+            host,
+            path: super::query::percent_decode(u.path(), false),
+            query: u.query().unwrap_or_default().to_string(),
+            fragment: super::query::percent_decode(u.fragment().unwrap_or_default(), false),
+            applied: None,
+        }
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing_slash = false;
+    for segment in path.split('/') {
+        trailing_slash = true;
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            s => {
+                segments.push(s);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    let mut str = String::with_capacity(path.len());
+    if path.starts_with('/') {
+        str.push('/');
+    }
+    str += segments.join("/").as_str();
+    if trailing_slash && !segments.is_empty() {
+        str.push('/');
+    }
+    str
+}
+
+/// Percent-encode the characters not allowed in a path, see RFC 3986 section 3.3.
+pub(crate) fn escape_path(path: &str) -> String {
+    let mut str = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => str.push(b as char),
+            b'-' | b'.' | b'_' | b'~' | b'/' | b':' | b'@' | b'!' | b'$' | b'&' | b'\'' => {
+                str.push(b as char)
+            }
+            b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' => str.push(b as char),
+            _ => str += format!("%{:02X}", b).as_str(),
+        }
+    }
+    str
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{reset_host, MockHost, MockRequest};
+    use crate::request;
+
+    fn install(path: &str, query: &str) -> MockHost {
+        let host = MockHost::new(
+            MockRequest::new("GET", path)
+                .with_host("example.com")
+                .with_query(query),
+        );
+        host.install();
+        host
+    }
+
+    // change the request behind the URL, so a component keeps this value unless it is written
+    fn change_behind(host: &MockHost) {
+        host.set_request(
+            MockRequest::new("GET", "/behind")
+                .with_host("behind")
+                .with_query("behind=1"),
+        );
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        for (path, normalized) in [
+            ("/a/./b/../c//d", "/a/c/d"),
+            ("/a/b/..", "/a/"),
+            ("/a/b/.", "/a/b/"),
+            ("/a//b//", "/a/b/"),
+            ("/../a", "/a"),
+            ("/..", "/"),
+            ("//", "/"),
+            ("/", "/"),
+            ("a/../b", "b"),
+            ("", ""),
+        ] {
+            assert_eq!(normalize_path(path), normalized, "{}", path);
+        }
+    }
+
+    #[test]
+    fn splits_segments() {
+        install("/", "");
+        let mut url = request::get_url();
+        let mut segments = |path: &str| {
+            url.set_path(path);
+            url.segments().map(str::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(segments("/a/b"), vec!["a", "b"]);
+        assert_eq!(segments("/a/b/"), vec!["a", "b", ""]);
+        assert_eq!(segments("a"), vec!["a"]);
+        assert_eq!(segments("/"), vec![""]);
+        assert!(segments("").is_empty());
+        reset_host();
+    }
+
+    #[test]
+    fn formats_escaped() {
+        let host = install("/a b/ü", "q=1");
+        host.set_request(MockRequest {
+            fragment: "x y".to_string(),
+            scheme: "https".to_string(),
+            ..host.request()
+        });
+        let url = request::get_url();
+        assert_eq!(url.escaped_path(), "/a%20b/%C3%BC");
+        assert_eq!(
+            url.to_string(),
+            "https://example.com/a%20b/%C3%BC?q=1#x%20y"
+        );
+        reset_host();
+    }
+
+    #[test]
+    fn applies_changed_components_only() {
+        let host = install("/a/../b", "x=1");
+        let mut url = request::get_url();
+        change_behind(&host);
+
+        // nothing has changed, so nothing is written
+        url.apply();
+        let req = host.request();
+        assert_eq!(
+            (req.host.as_str(), req.path.as_str()),
+            ("behind", "/behind")
+        );
+        assert_eq!(req.query, "behind=1");
+
+        url.normalize();
+        url.apply();
+        let req = host.request();
+        assert_eq!((req.host.as_str(), req.path.as_str()), ("behind", "/b"));
+        assert_eq!(req.query, "behind=1");
+
+        change_behind(&host);
+        let mut params = url.query_params();
+        params.set("y", "2");
+        url.set_query_params(&params);
+        url.set_host("backend");
+        url.apply();
+        let req = host.request();
+        assert_eq!(
+            (req.host.as_str(), req.path.as_str()),
+            ("backend", "/behind")
+        );
+        assert_eq!(req.query, "x=1&y=2");
+
+        // a component set back to its applied value is not written either
+        change_behind(&host);
+        url.set_path("/c");
+        url.set_path("/b");
+        url.apply();
+        assert_eq!(host.request().path, "/behind");
+        reset_host();
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn applies_all_components_of_a_parsed_url() {
+        let host = install("/", "");
+        let parsed = url::Url::parse("http://backend:8080/a%20b?x=1#f").unwrap();
+        let mut url = Url::from(&parsed);
+        assert_eq!(url.path(), "/a b");
+        assert_eq!(url::Url::try_from(&url).unwrap(), parsed);
+
+        change_behind(&host);
+        url.apply();
+        let req = host.request();
+        assert_eq!(
+            (req.host.as_str(), req.path.as_str()),
+            ("backend:8080", "/a b")
+        );
+        assert_eq!(req.query, "x=1");
+        reset_host();
+    }
+}
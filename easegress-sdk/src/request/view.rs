@@ -0,0 +1,296 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::cell::OnceCell;
+
+use crate::cookie::Cookie;
+use crate::header::{self, HeaderMap, InvalidHeader};
+use crate::host::with_host;
+use crate::marshal::{marshal_all_header, unmarshal_all_header};
+use crate::method::Method;
+
+use super::{QueryParams, Url};
+
+/// `Request` is a handle to the request being processed by the filter.
+///
+/// Values are fetched from Easegress on first access and cached, so reading the same header or
+/// the body several times crosses the Wasm boundary only once. Setters write through to Easegress
+/// and update or invalidate the cached values they affect.
+///
+/// ```ignore
+/// let mut req = Request::new();
+/// if req.path().starts_with("/admin") && req.header("Authorization").is_none() {
+///     req.set_path("/login");
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Request {
+    real_ip: OnceCell<String>,
+    scheme: OnceCell<String>,
+    proto: OnceCell<String>,
+    method: OnceCell<String>,
+    host: OnceCell<String>,
+    path: OnceCell<String>,
+    escaped_path: OnceCell<String>,
+    query: OnceCell<String>,
+    fragment: OnceCell<String>,
+    headers: OnceCell<HeaderMap>,
+    cookies: OnceCell<Vec<Cookie>>,
+    body: OnceCell<Vec<u8>>,
+}
+
+impl Request {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn real_ip(&self) -> &str {
+        self.real_ip
+            .get_or_init(|| with_host(|h| h.req_get_real_ip()))
+    }
+
+    pub fn proto(&self) -> &str {
+        self.proto.get_or_init(|| with_host(|h| h.req_get_proto()))
+    }
+
+    pub fn method(&self) -> &str {
+        self.method
+            .get_or_init(|| with_host(|h| h.req_get_method()))
+    }
+
+    pub fn typed_method(&self) -> Method {
+        Method::from_trusted(self.method())
+    }
+
+    /// Set the method, `Method` can be passed as well as a string.
+    pub fn set_method(&mut self, method: impl AsRef<str>) {
+        let method = method.as_ref();
+        with_host(|h| h.req_set_method(method));
+        self.method = OnceCell::from(method.to_string());
+    }
+
+    pub fn scheme(&self) -> &str {
+        self.scheme
+            .get_or_init(|| with_host(|h| h.req_get_scheme()))
+    }
+
+    pub fn host(&self) -> &str {
+        self.host.get_or_init(|| with_host(|h| h.req_get_host()))
+    }
+
+    pub fn set_host(&mut self, host: impl AsRef<str>) {
+        let host = host.as_ref();
+        with_host(|h| h.req_set_host(host));
+        self.host = OnceCell::from(host.to_string());
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.get_or_init(|| with_host(|h| h.req_get_path()))
+    }
+
+    pub fn set_path(&mut self, path: impl AsRef<str>) {
+        let path = path.as_ref();
+        with_host(|h| h.req_set_path(path));
+        self.path = OnceCell::from(path.to_string());
+        self.escaped_path.take();
+    }
+
+    pub fn escaped_path(&self) -> &str {
+        self.escaped_path
+            .get_or_init(|| with_host(|h| h.req_get_escaped_path()))
+    }
+
+    pub fn query(&self) -> &str {
+        self.query.get_or_init(|| with_host(|h| h.req_get_query()))
+    }
+
+    pub fn set_query(&mut self, query: impl AsRef<str>) {
+        let query = query.as_ref();
+        with_host(|h| h.req_set_query(query));
+        self.query = OnceCell::from(query.to_string());
+    }
+
+    /// Parse the query string into `QueryParams`.
+    pub fn query_params(&self) -> QueryParams {
+        QueryParams::parse(self.query())
+    }
+
+    pub fn set_query_params(&mut self, params: &QueryParams) {
+        self.set_query(params.encode());
+    }
+
+    pub fn fragment(&self) -> &str {
+        self.fragment
+            .get_or_init(|| with_host(|h| h.req_get_fragment()))
+    }
+
+    /// Return the URL assembled from the scheme, host, path, query and fragment.
+    pub fn url(&self) -> Url {
+        Url::from_request(self)
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.headers
+            .get_or_init(|| unmarshal_all_header(&with_host(|h| h.req_get_all_header())))
+    }
+
+    /// Return the first value of header `name`, the lookup is case-insensitive.
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers().get(name.as_ref())
+    }
+
+    /// Set header `name`, replacing its values. An invalid name or value is not sent to Easegress.
+    pub fn set_header(
+        &mut self,
+        name: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        header::check(name, value)?;
+        with_host(|h| h.req_set_header(name, value));
+        self.headers.take();
+        self.cookies.take();
+        Ok(())
+    }
+
+    /// Add a value to header `name`. An invalid name or value is not sent to Easegress.
+    pub fn add_header(
+        &mut self,
+        name: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        header::check(name, value)?;
+        with_host(|h| h.req_add_header(name, value));
+        self.headers.take();
+        self.cookies.take();
+        Ok(())
+    }
+
+    pub fn del_header(&mut self, name: impl AsRef<str>) {
+        with_host(|h| h.req_del_header(name.as_ref()));
+        self.headers.take();
+        self.cookies.take();
+    }
+
+    /// Replace all headers with `headers`.
+    pub fn set_headers(&mut self, headers: &HeaderMap) {
+        let str = marshal_all_header(headers);
+        with_host(|h| h.req_set_all_header(&str));
+        self.headers.take();
+        self.cookies.take();
+    }
+
+    pub fn cookies(&self) -> &[Cookie] {
+        self.cookies.get_or_init(|| {
+            let strs = with_host(|h| h.req_get_all_cookie());
+            strs.into_iter().filter_map(Cookie::unmarshal).collect()
+        })
+    }
+
+    pub fn cookie(&self, name: impl AsRef<str>) -> Option<&Cookie> {
+        let name = name.as_ref();
+        self.cookies().iter().find(|c| c.get_name() == name)
+    }
+
+    pub fn add_cookie(&mut self, c: &Cookie) {
+        if let Some(str) = c.marshal_or_log() {
+            with_host(|h| h.req_add_cookie(&str));
+            self.cookies.take();
+            self.headers.take();
+        }
+    }
+
+    pub fn body(&self) -> &[u8] {
+        self.body.get_or_init(|| with_host(|h| h.req_get_body()))
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Vec<u8>>) {
+        let body = body.into();
+        with_host(|h| h.req_set_body(&body));
+        self.body = OnceCell::from(body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{reset_host, MockHost, MockRequest};
+
+    #[test]
+    fn caches_values_until_set() {
+        let host = MockHost::new(
+            MockRequest::new("GET", "/a")
+                .with_header("Foo", "1")
+                .with_body("body"),
+        );
+        host.install();
+
+        let mut req = Request::new();
+        assert_eq!(req.path(), "/a");
+        assert_eq!(req.header("foo"), Some("1"));
+        assert_eq!(req.body(), b"body");
+
+        // changes made behind the handle are not seen until the cache is invalidated
+        host.set_request(MockRequest::new("POST", "/b").with_header("Foo", "2"));
+        assert_eq!(req.path(), "/a");
+        assert_eq!(req.header("Foo"), Some("1"));
+        assert_eq!(req.body(), b"body");
+        assert_eq!(req.method(), "POST");
+
+        req.set_path("/c");
+        assert_eq!(req.path(), "/c");
+        assert_eq!(req.escaped_path(), "/c");
+        assert_eq!(host.request().path, "/c");
+
+        req.add_header("Bar", "3").unwrap();
+        assert_eq!(req.header("Foo"), Some("2"));
+        assert_eq!(req.header("Bar"), Some("3"));
+
+        req.set_body("new");
+        assert_eq!(req.body(), b"new");
+        assert_eq!(host.request().body, b"new");
+        reset_host();
+    }
+
+    #[test]
+    fn invalidates_cookies_with_headers() {
+        let host = MockHost::new(MockRequest::new("GET", "/").with_cookie("a", "1"));
+        host.install();
+
+        let mut req = Request::new();
+        assert_eq!(req.cookie("a").map(Cookie::get_value), Some("1"));
+
+        req.set_header("Cookie", "b=2").unwrap();
+        assert!(req.cookie("a").is_none());
+        assert_eq!(req.cookie("b").map(Cookie::get_value), Some("2"));
+
+        req.add_header("Cookie", "c=3").unwrap();
+        assert_eq!(req.cookies().len(), 2);
+
+        req.del_header("Cookie");
+        assert!(req.cookies().is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.append("Cookie", "d=4").unwrap();
+        req.set_headers(&headers);
+        assert_eq!(req.cookie("d").map(Cookie::get_value), Some("4"));
+
+        req.add_cookie(&Cookie::new("e", "5"));
+        assert_eq!(req.cookies().len(), 2);
+        assert_eq!(req.header("Cookie"), Some("d=4; e=5"));
+        reset_host();
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let host = MockHost::new(MockRequest::new("GET", "/"));
+        host.install();
+
+        let mut req = Request::new();
+        assert!(req.set_header("Foo", "a\nb").is_err());
+        assert!(req.add_header("Foo Bar", "a").is_err());
+        assert!(req.header("Foo").is_none());
+        assert!(host.request().headers.is_empty());
+        reset_host();
+    }
+}
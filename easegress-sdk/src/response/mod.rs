@@ -1,90 +1,140 @@
 // Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
 
 use crate::cookie::Cookie;
-use crate::marshal::{
-    marshal_all_header, marshal_cookie, marshal_data, marshal_string, unmarshal_all_header,
-    unmarshal_data, unmarshal_string,
-};
-use std::collections::HashMap;
-
-#[link(wasm_import_module = "easegress")]
-extern "C" {
-    fn host_resp_get_status_code() -> i32;
-    fn host_resp_set_status_code(code: i32);
-    fn host_resp_get_header(addr: i32) -> i32;
-    fn host_resp_get_all_header() -> i32;
-    fn host_resp_set_header(name_addr: i32, value_addr: i32);
-    fn host_resp_set_all_header(addr: i32);
-    fn host_resp_add_header(name_addr: i32, value_addr: i32);
-    fn host_resp_del_header(addr: i32);
-    fn host_resp_set_cookie(addr: i32);
-    fn host_resp_get_body() -> i32;
-    fn host_resp_set_body(addr: i32);
-}
+use crate::header::{self, HeaderMap};
+use crate::host::with_host;
+use crate::marshal::{marshal_all_header, unmarshal_all_header};
+use crate::status::{InvalidStatusCode, StatusCode};
+
+mod respond;
+mod view;
 
+pub use respond::{respond, Responder};
+pub use view::Response;
+
+/// Return the status code, or an `InvalidStatusCode` holding the value if it is out of
+/// `100..=999`, e.g. `0`.
 #[no_mangle]
-pub fn get_status_code() -> i32 {
-    unsafe { host_resp_get_status_code() }
+pub fn get_status_code() -> Result<StatusCode, InvalidStatusCode> {
+    let code = with_host(|h| h.resp_get_status_code());
+    StatusCode::try_from(code)
 }
 
 #[no_mangle]
-pub fn set_status_code(code: i32) {
-    unsafe { host_resp_set_status_code(code) }
+pub fn set_status_code(code: StatusCode) {
+    with_host(|h| h.resp_set_status_code(code.into()))
 }
 
 #[no_mangle]
 pub fn resp_get_header(name: String) -> String {
-    let ptr = marshal_string(name);
-    let data = unsafe { host_resp_get_header(ptr.as_ptr() as i32) };
-    unmarshal_string(data)
+    with_host(|h| h.resp_get_header(&name))
 }
 
 #[no_mangle]
-pub fn resp_get_all_header() -> HashMap<String, Vec<String>> {
-    let ptr = unsafe { host_resp_get_all_header() };
-    unmarshal_all_header(ptr)
+pub fn resp_get_all_header() -> HeaderMap {
+    let headers = with_host(|h| h.resp_get_all_header());
+    unmarshal_all_header(&headers)
 }
 
+/// Set header `name`, an invalid name or value is logged and the header is dropped.
 #[no_mangle]
 pub fn resp_set_header(name: String, value: String) {
-    let name_ptr = marshal_string(name);
-    let value_ptr = marshal_string(value);
-    unsafe { host_resp_set_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    if header::check_or_log(&name, &value) {
+        with_host(|h| h.resp_set_header(&name, &value))
+    }
 }
 
 #[no_mangle]
-pub fn resp_set_all_header(headers: HashMap<String, Vec<String>>) {
-    let ptr = marshal_all_header(headers);
-    unsafe { host_resp_set_all_header(ptr.as_ptr() as i32) }
+pub fn resp_set_all_header(headers: &HeaderMap) {
+    let headers = marshal_all_header(headers);
+    with_host(|h| h.resp_set_all_header(&headers))
 }
 
+/// Add header `name`, an invalid name or value is logged and the header is dropped.
 #[no_mangle]
 pub fn resp_add_header(name: String, value: String) {
-    let name_ptr = marshal_string(name);
-    let value_ptr = marshal_string(value);
-    unsafe { host_resp_add_header(name_ptr.as_ptr() as i32, value_ptr.as_ptr() as i32) }
+    if header::check_or_log(&name, &value) {
+        with_host(|h| h.resp_add_header(&name, &value))
+    }
 }
 
 #[no_mangle]
 pub fn resp_del_header(name: String) {
-    let ptr = marshal_string(name);
-    unsafe { host_resp_del_header(ptr.as_ptr() as i32) }
+    with_host(|h| h.resp_del_header(&name))
 }
 
-#[no_mangle]
-pub fn resp_set_cookie(c: Cookie) {
-    let ptr = marshal_cookie(c);
-    unsafe { host_resp_set_cookie(ptr.as_ptr() as i32) }
+pub fn resp_set_cookie(c: impl Into<Cookie>) {
+    if let Some(str) = c.into().marshal_or_log() {
+        with_host(|h| h.resp_set_cookie(&str))
+    }
 }
 
 #[no_mangle]
 pub fn resp_get_body() -> Vec<u8> {
-    let ptr = unsafe { host_resp_get_body() };
-    unmarshal_data(ptr)
+    with_host(|h| h.resp_get_body())
 }
 
 #[no_mangle]
 pub fn resp_set_body(body: Vec<u8>) {
-    let ptr = marshal_data(body);
-    unsafe { host_resp_set_body(ptr.as_ptr() as i32) }
+    with_host(|h| h.resp_set_body(&body))
+}
+
+/// Decode the body as JSON, the Content-Type must be a JSON media type.
+#[cfg(feature = "serde_json")]
+pub fn resp_get_json<T: serde::de::DeserializeOwned>() -> Result<T, crate::json::JsonError> {
+    let content_type = resp_get_header("Content-Type".to_string());
+    crate::json::decode(&content_type, &resp_get_body())
+}
+
+/// Set the body to `value` encoded as JSON, and the Content-Type to `application/json`.
+#[cfg(feature = "serde_json")]
+pub fn resp_set_json<T: serde::Serialize + ?Sized>(
+    value: &T,
+) -> Result<(), crate::json::JsonError> {
+    let body = crate::json::encode(value)?;
+    resp_set_header(
+        "Content-Type".to_string(),
+        crate::json::CONTENT_TYPE.to_string(),
+    );
+    resp_set_body(body);
+    Ok(())
+}
+
+/// Apply the JSON Patch `patch` to the body, the Content-Type must be a JSON media type and is
+/// kept as is.
+#[cfg(feature = "serde_json")]
+pub fn resp_patch_json(patch: &crate::json::Patch) -> Result<(), crate::json::JsonError> {
+    let content_type = resp_get_header("Content-Type".to_string());
+    let body = crate::json::patch(&content_type, &resp_get_body(), patch)?;
+    resp_set_body(body);
+    Ok(())
+}
+
+/// Return the body decoded according to the Content-Encoding, failing with
+/// `EncodingError::TooLarge` if it exceeds `max_size` bytes.
+#[cfg(feature = "compression")]
+pub fn resp_get_decoded_body(
+    max_size: usize,
+) -> Result<Vec<u8>, crate::content_encoding::EncodingError> {
+    let encoding = resp_get_header("Content-Encoding".to_string());
+    crate::content_encoding::decode(&encoding, &resp_get_body(), max_size)
+}
+
+/// Set the body to `body` encoded according to the Content-Encoding, and update the
+/// Content-Length.
+#[cfg(feature = "compression")]
+pub fn resp_set_decoded_body(body: Vec<u8>) -> Result<(), crate::content_encoding::EncodingError> {
+    let encoding = resp_get_header("Content-Encoding".to_string());
+    let body = crate::content_encoding::encode(&encoding, &body)?;
+    resp_set_header("Content-Length".to_string(), body.len().to_string());
+    resp_set_body(body);
+    Ok(())
+}
+
+/// Set the body to `body` as is, remove the Content-Encoding and update the Content-Length.
+#[cfg(feature = "compression")]
+pub fn resp_set_identity_body(body: Vec<u8>) {
+    resp_del_header("Content-Encoding".to_string());
+    resp_set_header("Content-Length".to_string(), body.len().to_string());
+    resp_set_body(body)
 }
@@ -0,0 +1,135 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use crate::header::HeaderMap;
+use crate::status::StatusCode;
+use crate::{log, LogLevel, RunResult};
+
+/// Start building a full response with `status`, which is sent by `Responder::send`.
+///
+/// ```ignore
+/// fn run(&self) -> RunResult {
+///     if request::get_header("Authorization".to_string()).is_empty() {
+///         return response::respond(StatusCode::UNAUTHORIZED)
+///             .header("WWW-Authenticate", "Bearer")
+///             .text("missing token")
+///             .send();
+///     }
+///     RunResult::Continue
+/// }
+/// ```
+pub fn respond(status: StatusCode) -> Responder {
+    Responder {
+        status,
+        headers: HeaderMap::new(),
+        body: Vec::new(),
+        result: RunResult::Result1,
+    }
+}
+
+/// `Responder` builds a response replacing the one of the request being processed, see `respond`.
+#[must_use = "the response is not sent until `send` is called"]
+#[derive(Debug, Clone)]
+pub struct Responder {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    result: RunResult,
+}
+
+impl Responder {
+    /// Add a header, an invalid header is skipped with a warning in the log.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        if let Err(e) = self.headers.append(name, value) {
+            log(LogLevel::Warning, format!("respond: skip {}", e));
+        }
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set the body to `text` with a `text/plain` Content-Type.
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.content_type("text/plain; charset=utf-8")
+            .body(text.into())
+    }
+
+    /// Set the body to `value` serialized as JSON with an `application/json` Content-Type.
+    #[cfg(feature = "serde_json")]
+    pub fn json<T: serde::Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Self, crate::json::JsonError> {
+        let body = crate::json::encode(value)?;
+        Ok(self.content_type(crate::json::CONTENT_TYPE).body(body))
+    }
+
+    /// Set the value `send` returns, it is `RunResult::Result1` by default. Use `jumpIf` in the
+    /// pipeline spec to map the result to `END`, so the request is not sent to the backend.
+    pub fn result(mut self, result: RunResult) -> Self {
+        self.result = result;
+        self
+    }
+
+    /// Set the status code, headers and body of the response, and return the result for `run`.
+    ///
+    /// The headers of the response are replaced by the ones of the responder, so nothing of a
+    /// previous response, like its Content-Length or Content-Encoding, is sent with the new body.
+    /// Flush a `CookieJar` after `send` for its cookies to be sent.
+    pub fn send(self) -> RunResult {
+        super::set_status_code(self.status);
+        super::resp_set_all_header(&self.headers);
+        super::resp_set_body(self.body);
+        self.result
+    }
+
+    fn content_type(mut self, value: &str) -> Self {
+        self.headers.remove("Content-Type");
+        self.header("Content-Type", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::host::{self, MockHost, MockResponse};
+
+    use super::*;
+
+    #[test]
+    fn replaces_the_response() {
+        let host = MockHost::default();
+        host.set_response(
+            MockResponse::new(200)
+                .with_header("Content-Length", "1024")
+                .with_header("Content-Encoding", "gzip")
+                .with_header("Transfer-Encoding", "chunked")
+                .with_header("Content-Type", "application/octet-stream")
+                .with_body(vec![0; 1024]),
+        );
+        host::set_host(host.clone());
+
+        let result = respond(StatusCode::FORBIDDEN)
+            .header("X-Reason", "denied")
+            .text("forbidden")
+            .result(RunResult::Result2)
+            .send();
+        assert_eq!(result, RunResult::Result2);
+
+        let resp = host.response();
+        assert_eq!(resp.status_code, 403);
+        assert_eq!(
+            resp.headers,
+            vec![
+                ("X-Reason".to_string(), "denied".to_string()),
+                (
+                    "Content-Type".to_string(),
+                    "text/plain; charset=utf-8".to_string()
+                ),
+            ]
+        );
+        assert_eq!(resp.body, b"forbidden".to_vec());
+        host::reset_host();
+    }
+}
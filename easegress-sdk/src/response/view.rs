@@ -0,0 +1,106 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::cell::OnceCell;
+
+use crate::cookie::Cookie;
+use crate::header::{self, HeaderMap, InvalidHeader};
+use crate::host::with_host;
+use crate::marshal::{marshal_all_header, unmarshal_all_header};
+use crate::status::{InvalidStatusCode, StatusCode};
+
+/// `Response` is a handle to the response of the request being processed by the filter.
+///
+/// Like `Request`, values are fetched from Easegress on first access and cached, and setters
+/// write through to Easegress.
+#[derive(Debug, Default)]
+pub struct Response {
+    status_code: OnceCell<Result<StatusCode, InvalidStatusCode>>,
+    headers: OnceCell<HeaderMap>,
+    body: OnceCell<Vec<u8>>,
+}
+
+impl Response {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the status code, or an `InvalidStatusCode` holding the value if it is out of
+    /// `100..=999`, e.g. `0`.
+    pub fn status_code(&self) -> Result<StatusCode, InvalidStatusCode> {
+        *self.status_code.get_or_init(|| {
+            let code = with_host(|h| h.resp_get_status_code());
+            StatusCode::try_from(code)
+        })
+    }
+
+    pub fn set_status_code(&mut self, code: StatusCode) {
+        with_host(|h| h.resp_set_status_code(code.into()));
+        self.status_code = OnceCell::from(Ok(code));
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.headers
+            .get_or_init(|| unmarshal_all_header(&with_host(|h| h.resp_get_all_header())))
+    }
+
+    /// Return the first value of header `name`, the lookup is case-insensitive.
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers().get(name.as_ref())
+    }
+
+    /// Set header `name`, replacing its values. An invalid name or value is not sent to Easegress.
+    pub fn set_header(
+        &mut self,
+        name: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        header::check(name, value)?;
+        with_host(|h| h.resp_set_header(name, value));
+        self.headers.take();
+        Ok(())
+    }
+
+    /// Add a value to header `name`. An invalid name or value is not sent to Easegress.
+    pub fn add_header(
+        &mut self,
+        name: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<(), InvalidHeader> {
+        let (name, value) = (name.as_ref(), value.as_ref());
+        header::check(name, value)?;
+        with_host(|h| h.resp_add_header(name, value));
+        self.headers.take();
+        Ok(())
+    }
+
+    pub fn del_header(&mut self, name: impl AsRef<str>) {
+        with_host(|h| h.resp_del_header(name.as_ref()));
+        self.headers.take();
+    }
+
+    /// Replace all headers with `headers`.
+    pub fn set_headers(&mut self, headers: &HeaderMap) {
+        let str = marshal_all_header(headers);
+        with_host(|h| h.resp_set_all_header(&str));
+        self.headers.take();
+    }
+
+    /// Add a `Set-Cookie` header for `c`.
+    pub fn set_cookie(&mut self, c: &Cookie) {
+        if let Some(str) = c.marshal_or_log() {
+            with_host(|h| h.resp_set_cookie(&str));
+            self.headers.take();
+        }
+    }
+
+    pub fn body(&self) -> &[u8] {
+        self.body.get_or_init(|| with_host(|h| h.resp_get_body()))
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Vec<u8>>) {
+        let body = body.into();
+        with_host(|h| h.resp_set_body(&body));
+        self.body = OnceCell::from(body);
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! A path based router to dispatch requests to different handlers inside a single `Program`.
+//!
+//! A pattern is a list of `/` separated segments. A segment is either a literal, a parameter
+//! like `:id` which matches exactly one non-empty segment, or a wildcard like `*` or `*rest` which
+//! must be the last segment and matches the remaining path, including nothing. Routes are tried in
+//! the order they were added.
+//!
+//! Paths are matched in their escaped form and each segment is decoded on its own, so an escaped
+//! `/` (`%2F`) stays in the parameter it belongs to. A trailing `/` of the path is ignored, so
+//! `/users/1/` matches `/users/:id`. A `HEAD` request matching no route is dispatched to the
+//! routes of `GET`, like Easegress answers `HEAD` with the response to `GET` without the body.
+//!
+//! ```ignore
+//! struct Filter {
+//!     router: Router<Filter>,
+//! }
+//!
+//! impl Filter {
+//!     fn get_user(&self, params: &Params) -> RunResult {
+//!         let id = params.get("id").unwrap();
+//!         RunResult::Continue
+//!     }
+//! }
+//!
+//! let router = Router::new()
+//!     .get("/api/v1/users/:id", Filter::get_user)
+//!     .any("/admin/*", |_: &Filter, _: &Params| RunResult::Result1)
+//!     .fallback(|_: &Filter, _: &Params| RunResult::Continue);
+//!
+//! // in `Program::run`
+//! self.router.dispatch(self)
+//! ```
+
+use crate::method::Method;
+use crate::request::{self, percent_decode};
+use crate::RunResult;
+
+/// `Handler` handles the requests matching a route. It is implemented for closures and functions
+/// taking the state and the path parameters.
+pub trait Handler<S> {
+    fn handle(&self, state: &S, params: &Params) -> RunResult;
+}
+
+impl<S, F> Handler<S> for F
+where
+    F: Fn(&S, &Params) -> RunResult,
+{
+    fn handle(&self, state: &S, params: &Params) -> RunResult {
+        self(state, params)
+    }
+}
+
+/// `Params` holds the parameters extracted from the path, in the order they appear in the pattern.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    pairs: Vec<(String, String)>,
+}
+
+impl Params {
+    /// Return the value of parameter `name`, use `*` for an anonymous wildcard.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// `Router` dispatches a request to the handler of the first route matching its method and path.
+pub struct Router<S> {
+    routes: Vec<Route<S>>,
+    fallback: Option<Box<dyn Handler<S>>>,
+}
+
+struct Route<S> {
+    method: Option<Method>,
+    segments: Vec<Segment>,
+    handler: Box<dyn Handler<S>>,
+}
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+impl<S> Default for Router<S> {
+    fn default() -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+}
+
+impl<S> Router<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a route for requests with `method` and a path matching `pattern`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` does not start with `/`, if a parameter has no name, or if a wildcard
+    /// is not the last segment.
+    pub fn route(self, method: Method, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.add(Some(method), pattern, handler)
+    }
+
+    /// Add a route for requests with any method and a path matching `pattern`.
+    pub fn any(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.add(None, pattern, handler)
+    }
+
+    pub fn get(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.route(Method::Get, pattern, handler)
+    }
+
+    pub fn post(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.route(Method::Post, pattern, handler)
+    }
+
+    pub fn put(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.route(Method::Put, pattern, handler)
+    }
+
+    pub fn patch(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.route(Method::Patch, pattern, handler)
+    }
+
+    pub fn delete(self, pattern: &str, handler: impl Handler<S> + 'static) -> Self {
+        self.route(Method::Delete, pattern, handler)
+    }
+
+    /// Set the handler for requests matching no route, it receives empty `Params`.
+    pub fn fallback(mut self, handler: impl Handler<S> + 'static) -> Self {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Dispatch the request being processed, see `dispatch_to`.
+    pub fn dispatch(&self, state: &S) -> RunResult {
+        self.dispatch_to(
+            &request::get_typed_method(),
+            &request::get_escape_path(),
+            state,
+        )
+    }
+
+    /// Call the handler of the first route matching `method` and the escaped `path`, or the
+    /// fallback handler. Returns `RunResult::Continue` if there is neither.
+    pub fn dispatch_to(&self, method: &Method, path: &str, state: &S) -> RunResult {
+        let mut found = self.find(method, path);
+        if found.is_none() && *method == Method::Head {
+            found = self.find(&Method::Get, path);
+        }
+        match (found, &self.fallback) {
+            (Some((handler, params)), _) => handler.handle(state, &params),
+            (None, Some(handler)) => handler.handle(state, &Params::default()),
+            (None, None) => RunResult::Continue,
+        }
+    }
+
+    fn find(&self, method: &Method, path: &str) -> Option<(&dyn Handler<S>, Params)> {
+        self.routes
+            .iter()
+            .filter(|route| route.method.as_ref().is_none_or(|m| m == method))
+            .find_map(|route| Some((route.handler.as_ref(), match_path(&route.segments, path)?)))
+    }
+
+    fn add(
+        mut self,
+        method: Option<Method>,
+        pattern: &str,
+        handler: impl Handler<S> + 'static,
+    ) -> Self {
+        self.routes.push(Route {
+            method,
+            segments: parse_pattern(pattern),
+            handler: Box::new(handler),
+        });
+        self
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Vec<Segment> {
+    let Some(rest) = pattern.strip_prefix('/') else {
+        panic!("route pattern {:?} must start with '/'", pattern);
+    };
+    let parts: Vec<&str> = rest.split('/').collect();
+    let mut segments = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let segment = if let Some(name) = part.strip_prefix(':') {
+            if name.is_empty() {
+                panic!("route pattern {:?} has a parameter without name", pattern);
+            }
+            Segment::Param(name.to_string())
+        } else if let Some(name) = part.strip_prefix('*') {
+            if i != parts.len() - 1 {
+                panic!(
+                    "route pattern {:?} has a wildcard before the last segment",
+                    pattern
+                );
+            }
+            let name = if name.is_empty() { "*" } else { name };
+            Segment::Wildcard(name.to_string())
+        } else {
+            Segment::Literal(part.to_string())
+        };
+        segments.push(segment);
+    }
+    segments
+}
+
+fn match_path(segments: &[Segment], path: &str) -> Option<Params> {
+    let mut rest = path.strip_prefix('/')?;
+    let mut params = Params::default();
+    for (i, segment) in segments.iter().enumerate() {
+        if let Segment::Wildcard(name) = segment {
+            params
+                .pairs
+                .push((name.clone(), percent_decode(rest, false)));
+            return Some(params);
+        }
+
+        let (part, next) = match rest.split_once('/') {
+            Some((part, next)) => (part, Some(next)),
+            None => (rest, None),
+        };
+        let part = percent_decode(part, false);
+        match segment {
+            Segment::Literal(literal) if *literal == part => {}
+            Segment::Param(name) if !part.is_empty() => params.pairs.push((name.clone(), part)),
+            _ => return None,
+        }
+
+        match next {
+            Some(next) => rest = next,
+            None if i == segments.len() - 1 => return Some(params),
+            // the path is shorter, only a trailing wildcard or slash can still match.
+            None => {
+                return match &segments[i + 1..] {
+                    [Segment::Wildcard(name)] => {
+                        params.pairs.push((name.clone(), String::new()));
+                        Some(params)
+                    }
+                    [Segment::Literal(literal)] if literal.is_empty() => Some(params),
+                    _ => None,
+                };
+            }
+        }
+    }
+    // the path has more segments than the pattern, which is fine for a trailing slash only.
+    rest.is_empty().then_some(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    type Calls = RefCell<Vec<String>>;
+
+    // a handler recording its name and the params in the state
+    fn handler(name: &'static str) -> impl Handler<Calls> {
+        move |calls: &Calls, params: &Params| {
+            let params: Vec<_> = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            calls
+                .borrow_mut()
+                .push(format!("{} {}", name, params.join(",")));
+            RunResult::Result1
+        }
+    }
+
+    fn router() -> Router<Calls> {
+        Router::new()
+            .get("/users/:id", handler("get_user"))
+            .route(Method::Head, "/head", handler("head"))
+            .get("/head", handler("get_head"))
+            .post("/users/:id/files/*path", handler("upload"))
+            .any("/static/*", handler("static"))
+            .get("/", handler("root"))
+    }
+
+    fn dispatch(router: &Router<Calls>, method: Method, path: &str) -> Option<String> {
+        let calls = Calls::default();
+        match router.dispatch_to(&method, path, &calls) {
+            RunResult::Continue => None,
+            _ => calls.into_inner().pop(),
+        }
+    }
+
+    #[test]
+    fn matches_params_and_wildcards() {
+        let router = router();
+        let dispatch = |method, path| dispatch(&router, method, path);
+        assert_eq!(dispatch(Method::Get, "/users/1").unwrap(), "get_user id=1");
+        assert_eq!(
+            dispatch(Method::Post, "/users/1/files/a/b").unwrap(),
+            "upload id=1,path=a/b"
+        );
+        assert_eq!(dispatch(Method::Put, "/static").unwrap(), "static *=");
+        assert_eq!(dispatch(Method::Get, "/").unwrap(), "root ");
+        assert_eq!(dispatch(Method::Get, "/users"), None);
+        assert_eq!(dispatch(Method::Get, "/users/"), None);
+        assert_eq!(dispatch(Method::Get, "/users/1/2"), None);
+        assert_eq!(dispatch(Method::Delete, "/users/1"), None);
+    }
+
+    #[test]
+    fn decodes_segments_of_the_escaped_path() {
+        let router = router();
+        let dispatch = |method, path| dispatch(&router, method, path);
+        assert_eq!(
+            dispatch(Method::Get, "/users/a%2Fb").unwrap(),
+            "get_user id=a/b"
+        );
+        assert_eq!(
+            dispatch(Method::Get, "/users/a%20b").unwrap(),
+            "get_user id=a b"
+        );
+        assert_eq!(
+            dispatch(Method::Post, "/users/%3A1/files/a%2Fb/c").unwrap(),
+            "upload id=:1,path=a/b/c"
+        );
+        assert_eq!(
+            dispatch(Method::Get, "/%75sers/1").unwrap(),
+            "get_user id=1"
+        );
+    }
+
+    #[test]
+    fn ignores_a_trailing_slash() {
+        let router = router();
+        let dispatch = |method, path| dispatch(&router, method, path);
+        assert_eq!(dispatch(Method::Get, "/users/1/").unwrap(), "get_user id=1");
+        assert_eq!(dispatch(Method::Get, "/users/1//"), None);
+        assert_eq!(
+            dispatch(Method::Post, "/users/1/files/").unwrap(),
+            "upload id=1,path="
+        );
+    }
+
+    #[test]
+    fn falls_back_from_head_to_get() {
+        let router = router();
+        let dispatch = |method, path| dispatch(&router, method, path);
+        assert_eq!(dispatch(Method::Head, "/head").unwrap(), "head ");
+        assert_eq!(dispatch(Method::Head, "/users/1").unwrap(), "get_user id=1");
+        assert_eq!(dispatch(Method::Head, "/static/a").unwrap(), "static *=a");
+        assert_eq!(dispatch(Method::Options, "/users/1"), None);
+    }
+
+    #[test]
+    fn calls_the_fallback() {
+        let router = router().fallback(handler("fallback"));
+        assert_eq!(
+            dispatch(&router, Method::Get, "/unknown").unwrap(),
+            "fallback "
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must start with '/'")]
+    fn rejects_relative_patterns() {
+        Router::<Calls>::new().get("users", handler("get_user"));
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Check the `i32` returned by the `wasm_run` which `#[easegress_object]` generates for a `run`
+//! returning `RunResult`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use easegress_macros::easegress_object;
+use easegress_sdk::*;
+
+#[easegress_object]
+struct Fixed {
+    result: RunResult,
+}
+
+#[easegress_object(init_failure = RunResult::Result3)]
+impl Program for Fixed {
+    fn new(params: HashMap<String, String>) -> Self {
+        let result: i32 = params["result"].parse().unwrap();
+        Self {
+            result: RunResult::try_from(result).unwrap(),
+        }
+    }
+
+    fn run(&self) -> RunResult {
+        self.result
+    }
+}
+
+#[test]
+fn wasm_run_returns_the_run_result() {
+    // not initialized yet
+    assert_eq!(wasm_run(), 3);
+
+    // set the program like `wasm_init`, which reads the parameters from Wasm memory
+    let params = HashMap::from([("result".to_string(), "7".to_string())]);
+    let program = Fixed::new(params);
+    INIT.call_once(|| unsafe { PROGRAM = Some(Mutex::new(program)) });
+    assert_eq!(wasm_run(), 7);
+}
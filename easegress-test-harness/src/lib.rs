@@ -0,0 +1,444 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! A local stand-in for the Easegress WasmHost filter.
+//!
+//! `Harness` loads a filter compiled with `cargo build --target wasm32-unknown-unknown` into
+//! wasmtime, provides every import of the `easegress` module on top of a `Host` (a `MockHost` by
+//! default), and drives the module through `wasm_alloc`, `wasm_init` and `wasm_run` the same way
+//! Easegress does, so the real `.wasm` artifact can be tested without a running cluster.
+//!
+//! # Examples
+//! ```ignore
+//! let params = HashMap::from([("maxPermission".to_string(), "3".to_string())]);
+//! let mut harness = Harness::from_file("target/wasm32-unknown-unknown/release/demo.wasm", &params)?;
+//!
+//! let result = harness.run_request(MockRequest::new("GET", "/").with_header("Foo", "bar"))?;
+//! assert_eq!(result, 0);
+//! assert_eq!(harness.host().request().get_header("Wasm-Added"), Some("bar"));
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use easegress_sdk::host::{Host, MockHost, MockRequest, MockResponse};
+use easegress_sdk::LogLevel;
+use wasmtime::{Caller, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+const MODULE: &str = "easegress";
+
+/// `Harness` is an instantiated filter module whose imports are served by a `Host`.
+pub struct Harness<H: Host + 'static = MockHost> {
+    store: Store<H>,
+    run: TypedFunc<(), i32>,
+    memory: Memory,
+}
+
+impl Harness<MockHost> {
+    /// Load the module at `path` and initialize it with `params` on a new `MockHost`.
+    pub fn from_file(path: impl AsRef<Path>, params: &HashMap<String, String>) -> Result<Self> {
+        let wasm = std::fs::read(path)?;
+        Self::new(wasm, params)
+    }
+
+    /// Load the module in `wasm` and initialize it with `params` on a new `MockHost`.
+    pub fn new(wasm: impl AsRef<[u8]>, params: &HashMap<String, String>) -> Result<Self> {
+        Self::with_host(wasm, MockHost::default(), params)
+    }
+
+    /// Replace the request and reset the response of the `MockHost`, then call `wasm_run`.
+    pub fn run_request(&mut self, request: MockRequest) -> Result<i32> {
+        self.host().set_request(request);
+        self.host().set_response(MockResponse::default());
+        self.run()
+    }
+}
+
+impl<H: Host + 'static> Harness<H> {
+    /// Load the module in `wasm`, serve its imports with `host` and initialize it with `params`.
+    pub fn with_host(
+        wasm: impl AsRef<[u8]>,
+        host: H,
+        params: &HashMap<String, String>,
+    ) -> Result<Self> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm)?;
+        let mut linker = Linker::new(&engine);
+        link_imports(&mut linker)?;
+
+        let mut store = Store::new(&engine, host);
+        let instance = linker.instantiate(&mut store, &module)?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "wasm_alloc")?;
+        let init = instance.get_typed_func::<i32, ()>(&mut store, "wasm_init")?;
+        let run = instance.get_typed_func::<(), i32>(&mut store, "wasm_run")?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("module does not export `memory`"))?;
+
+        let mut data = Vec::with_capacity(params.len() * 2);
+        for (k, v) in params {
+            data.push(k.clone());
+            data.push(v.clone());
+        }
+        let data = encode_string_vec(&data);
+        let ptr = alloc.call(&mut store, data.len() as i32)?;
+        memory.write(&mut store, ptr as usize, &data)?;
+        init.call(&mut store, ptr)?;
+
+        Ok(Self { store, run, memory })
+    }
+
+    pub fn host(&self) -> &H {
+        self.store.data()
+    }
+
+    /// Return the size of the linear memory of the module in bytes, it grows if the module leaks.
+    pub fn memory_size(&self) -> usize {
+        self.memory.data_size(&self.store)
+    }
+
+    /// Call `wasm_run`, i.e. what Easegress does for each request.
+    pub fn run(&mut self) -> Result<i32> {
+        self.run.call(&mut self.store, ())
+    }
+}
+
+fn link_imports<H: Host + 'static>(linker: &mut Linker<H>) -> Result<()> {
+    link_set_string(linker, "host_add_tag", H::add_tag)?;
+    linker.func_wrap(
+        MODULE,
+        "host_log",
+        |mut caller: Caller<'_, H>, level: i32, addr: i32| -> Result<()> {
+            let msg = read_string(&mut caller, addr)?;
+            caller.data().log(log_level(level), &msg);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_get_unix_time_in_ms",
+        |caller: Caller<'_, H>| caller.data().get_unix_time_in_ms(),
+    )?;
+    linker.func_wrap(MODULE, "host_rand", |caller: Caller<'_, H>| {
+        caller.data().rand()
+    })?;
+
+    link_get_string(linker, "host_req_get_real_ip", H::req_get_real_ip)?;
+    link_get_string(linker, "host_req_get_scheme", H::req_get_scheme)?;
+    link_get_string(linker, "host_req_get_proto", H::req_get_proto)?;
+    link_get_string(linker, "host_req_get_method", H::req_get_method)?;
+    link_set_string(linker, "host_req_set_method", H::req_set_method)?;
+    link_get_string(linker, "host_req_get_host", H::req_get_host)?;
+    link_set_string(linker, "host_req_set_host", H::req_set_host)?;
+    link_get_string(linker, "host_req_get_path", H::req_get_path)?;
+    link_set_string(linker, "host_req_set_path", H::req_set_path)?;
+    link_get_string(linker, "host_req_get_escaped_path", H::req_get_escaped_path)?;
+    link_get_string(linker, "host_req_get_query", H::req_get_query)?;
+    link_set_string(linker, "host_req_set_query", H::req_set_query)?;
+    link_get_string(linker, "host_req_get_fragment", H::req_get_fragment)?;
+    link_lookup_string(linker, "host_req_get_header", H::req_get_header)?;
+    link_get_string(linker, "host_req_get_all_header", H::req_get_all_header)?;
+    link_set_string_pair(linker, "host_req_set_header", H::req_set_header)?;
+    link_set_string(linker, "host_req_set_all_header", H::req_set_all_header)?;
+    link_set_string_pair(linker, "host_req_add_header", H::req_add_header)?;
+    link_set_string(linker, "host_req_del_header", H::req_del_header)?;
+    link_lookup_string(linker, "host_req_get_cookie", H::req_get_cookie)?;
+    linker.func_wrap(
+        MODULE,
+        "host_req_get_all_cookie",
+        |mut caller: Caller<'_, H>| -> Result<i32> {
+            let cookies = caller.data().req_get_all_cookie();
+            write(&mut caller, &encode_string_vec(&cookies))
+        },
+    )?;
+    link_set_string(linker, "host_req_add_cookie", H::req_add_cookie)?;
+    link_get_data(linker, "host_req_get_body", H::req_get_body)?;
+    link_set_data(linker, "host_req_set_body", H::req_set_body)?;
+
+    linker.func_wrap(
+        MODULE,
+        "host_resp_get_status_code",
+        |caller: Caller<'_, H>| caller.data().resp_get_status_code(),
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_resp_set_status_code",
+        |caller: Caller<'_, H>, code: i32| caller.data().resp_set_status_code(code),
+    )?;
+    link_lookup_string(linker, "host_resp_get_header", H::resp_get_header)?;
+    link_get_string(linker, "host_resp_get_all_header", H::resp_get_all_header)?;
+    link_set_string_pair(linker, "host_resp_set_header", H::resp_set_header)?;
+    link_set_string(linker, "host_resp_set_all_header", H::resp_set_all_header)?;
+    link_set_string_pair(linker, "host_resp_add_header", H::resp_add_header)?;
+    link_set_string(linker, "host_resp_del_header", H::resp_del_header)?;
+    link_set_string(linker, "host_resp_set_cookie", H::resp_set_cookie)?;
+    link_get_data(linker, "host_resp_get_body", H::resp_get_body)?;
+    link_set_data(linker, "host_resp_set_body", H::resp_set_body)?;
+
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_get_binary",
+        |mut caller: Caller<'_, H>, addr: i32| -> Result<i32> {
+            let key = read_string(&mut caller, addr)?;
+            let val = caller.data().cluster_get_binary(&key);
+            write(&mut caller, &encode_data(&val))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_put_binary",
+        |mut caller: Caller<'_, H>, key_addr: i32, val_addr: i32| -> Result<()> {
+            let key = read_string(&mut caller, key_addr)?;
+            let val = read_data(&mut caller, val_addr)?;
+            caller.data().cluster_put_binary(&key, &val);
+            Ok(())
+        },
+    )?;
+    link_lookup_string(linker, "host_cluster_get_string", H::cluster_get_string)?;
+    link_set_string_pair(linker, "host_cluster_put_string", H::cluster_put_string)?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_get_integer",
+        |mut caller: Caller<'_, H>, addr: i32| -> Result<i64> {
+            let key = read_string(&mut caller, addr)?;
+            Ok(caller.data().cluster_get_integer(&key))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_put_integer",
+        |mut caller: Caller<'_, H>, addr: i32, val: i64| -> Result<()> {
+            let key = read_string(&mut caller, addr)?;
+            caller.data().cluster_put_integer(&key, val);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_add_integer",
+        |mut caller: Caller<'_, H>, addr: i32, val: i64| -> Result<i64> {
+            let key = read_string(&mut caller, addr)?;
+            Ok(caller.data().cluster_add_integer(&key, val))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_get_float",
+        |mut caller: Caller<'_, H>, addr: i32| -> Result<f64> {
+            let key = read_string(&mut caller, addr)?;
+            Ok(caller.data().cluster_get_float(&key))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_put_float",
+        |mut caller: Caller<'_, H>, addr: i32, val: f64| -> Result<()> {
+            let key = read_string(&mut caller, addr)?;
+            caller.data().cluster_put_float(&key, val);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_add_float",
+        |mut caller: Caller<'_, H>, addr: i32, val: f64| -> Result<f64> {
+            let key = read_string(&mut caller, addr)?;
+            Ok(caller.data().cluster_add_float(&key, val))
+        },
+    )?;
+    linker.func_wrap(
+        MODULE,
+        "host_cluster_count_key",
+        |mut caller: Caller<'_, H>, addr: i32| -> Result<i32> {
+            let prefix = read_string(&mut caller, addr)?;
+            Ok(caller.data().cluster_count_key(&prefix))
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking no argument and returning a string.
+fn link_get_string<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H) -> String,
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>| -> Result<i32> {
+            let val = f(caller.data());
+            write(&mut caller, &encode_string(&val))
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking a string and returning a string.
+fn link_lookup_string<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H, &str) -> String,
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>, addr: i32| -> Result<i32> {
+            let key = read_string(&mut caller, addr)?;
+            let val = f(caller.data(), &key);
+            write(&mut caller, &encode_string(&val))
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking a string.
+fn link_set_string<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H, &str),
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>, addr: i32| -> Result<()> {
+            let val = read_string(&mut caller, addr)?;
+            f(caller.data(), &val);
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking two strings.
+fn link_set_string_pair<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H, &str, &str),
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>, name_addr: i32, value_addr: i32| -> Result<()> {
+            let name = read_string(&mut caller, name_addr)?;
+            let value = read_string(&mut caller, value_addr)?;
+            f(caller.data(), &name, &value);
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking no argument and returning binary data.
+fn link_get_data<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H) -> Vec<u8>,
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>| -> Result<i32> {
+            let val = f(caller.data());
+            write(&mut caller, &encode_data(&val))
+        },
+    )?;
+    Ok(())
+}
+
+/// Link an import taking binary data.
+fn link_set_data<H: Host + 'static>(
+    linker: &mut Linker<H>,
+    name: &str,
+    f: fn(&H, &[u8]),
+) -> Result<()> {
+    linker.func_wrap(
+        MODULE,
+        name,
+        move |mut caller: Caller<'_, H>, addr: i32| -> Result<()> {
+            let val = read_data(&mut caller, addr)?;
+            f(caller.data(), &val);
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+fn log_level(level: i32) -> LogLevel {
+    match level {
+        0 => LogLevel::Debug,
+        1 => LogLevel::Info,
+        2 => LogLevel::Warning,
+        _ => LogLevel::Error,
+    }
+}
+
+fn memory<H>(caller: &mut Caller<'_, H>) -> Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow!("module does not export `memory`"))
+}
+
+/// Read the length prefixed bytes at `addr`.
+fn read_prefixed<H>(caller: &mut Caller<'_, H>, addr: i32) -> Result<Vec<u8>> {
+    let memory = memory(caller)?;
+    let data = memory.data(&caller);
+    let start = addr as usize + 4;
+    let length = data
+        .get(addr as usize..start)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(|| anyhow!("address {} is out of bounds", addr))?;
+    data.get(start..start + length)
+        .map(|b| b.to_vec())
+        .ok_or_else(|| anyhow!("data at address {} is out of bounds", addr))
+}
+
+/// Read data marshaled by `marshal_data`.
+fn read_data<H>(caller: &mut Caller<'_, H>, addr: i32) -> Result<Vec<u8>> {
+    read_prefixed(caller, addr)
+}
+
+/// Read a string marshaled by `marshal_string`, whose length includes the trailing 0.
+fn read_string<H>(caller: &mut Caller<'_, H>, addr: i32) -> Result<String> {
+    let mut data = read_prefixed(caller, addr)?;
+    data.pop();
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+/// Copy `data` into a buffer allocated by `wasm_alloc` and return its address.
+fn write<H>(caller: &mut Caller<'_, H>, data: &[u8]) -> Result<i32> {
+    let alloc = caller
+        .get_export("wasm_alloc")
+        .and_then(|e| e.into_func())
+        .ok_or_else(|| anyhow!("module does not export `wasm_alloc`"))?;
+    let ptr = alloc
+        .typed::<i32, i32>(&*caller)?
+        .call(&mut *caller, data.len() as i32)?;
+    memory(caller)?.write(&mut *caller, ptr as usize, data)?;
+    Ok(ptr)
+}
+
+fn encode_data(data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 4);
+    buf.extend((data.len() as i32).to_le_bytes());
+    buf.extend(data);
+    buf
+}
+
+fn encode_string(str: &str) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(str.len() + 5);
+    buf.extend((str.len() as i32 + 1).to_le_bytes());
+    buf.extend(str.as_bytes());
+    buf.push(0);
+    buf
+}
+
+fn encode_string_vec(strs: &[String]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend((strs.len() as i32).to_le_bytes());
+    for str in strs {
+        buf.extend(encode_string(str));
+    }
+    buf
+}
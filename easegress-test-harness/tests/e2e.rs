@@ -0,0 +1,99 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+//! Load the `add-request-header` example as a real `.wasm` module and drive it through the harness.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use easegress_sdk::host::MockRequest;
+use easegress_sdk::LogLevel;
+use easegress_test_harness::Harness;
+
+/// Build the example for wasm32 once, in its own target directory so the build does not wait for
+/// the lock of the outer cargo.
+fn example() -> &'static Path {
+    static WASM: OnceLock<PathBuf> = OnceLock::new();
+    WASM.get_or_init(|| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let target = root.join("target").join("e2e");
+        let status = Command::new(env!("CARGO"))
+            .current_dir(root)
+            .env("CARGO_TARGET_DIR", &target)
+            .args(["build", "--release", "-p", "add-request-header"])
+            .args(["--target", "wasm32-unknown-unknown"])
+            .status()
+            .expect("cargo can be run");
+        assert!(status.success(), "the example does not build for wasm32");
+        target.join("wasm32-unknown-unknown/release/add_request_header.wasm")
+    })
+}
+
+fn harness(params: &[(&str, &str)]) -> Harness {
+    let params: HashMap<_, _> = params
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    Harness::from_file(example(), &params).unwrap()
+}
+
+#[test]
+fn runs_the_filter() {
+    let mut harness = harness(&[]);
+    let request = MockRequest::new("POST", "/")
+        .with_header("Foo", "bar")
+        .with_cookie("a", "1")
+        .with_cookie("b", "2")
+        .with_body("hello");
+    assert_eq!(harness.run_request(request).unwrap(), 0);
+
+    let request = harness.host().request();
+    assert_eq!(request.get_header("Wasm-Added"), Some("bar"));
+    assert_eq!(request.get_header("Wasm-Cookies"), Some("2"));
+    assert_eq!(
+        request.body,
+        b"I have a new body now, the old one had 5 bytes".to_vec()
+    );
+    assert!(harness.host().logs().is_empty());
+}
+
+#[test]
+fn passes_params_to_the_filter() {
+    let mut harness = harness(&[("maxLength", "2")]);
+    let request = MockRequest::new("GET", "/").with_header("Foo", "bar");
+    harness.run_request(request).unwrap();
+    assert_eq!(
+        harness.host().logs(),
+        vec![(
+            LogLevel::Warning,
+            "The length of Foo is greater than 2".to_string()
+        )]
+    );
+}
+
+#[test]
+fn releases_host_buffers() {
+    let mut harness = harness(&[]);
+    // wee_alloc, the allocator of the SDK, does not reuse large freed blocks such as 64KiB ones,
+    // so the body stays well below that
+    let body = vec![b'x'; 16 * 1024];
+    let run = |harness: &mut Harness| {
+        let request = MockRequest::new("POST", "/")
+            .with_header("Foo", "bar")
+            .with_cookie("a", "1")
+            .with_body(body.clone());
+        harness.run_request(request).unwrap();
+    };
+
+    // let the allocator reach its steady state first
+    for _ in 0..10 {
+        run(&mut harness);
+    }
+    let size = harness.memory_size();
+    for _ in 0..100 {
+        run(&mut harness);
+    }
+    // each run reads the 16KiB body from the host, which would grow the memory if leaked
+    assert_eq!(harness.memory_size(), size);
+}
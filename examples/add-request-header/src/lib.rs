@@ -0,0 +1,47 @@
+// Copyright (c) 2017, MegaEase All rights reserved. Licensed under the Apache License, Version 2.0 (the "License");
+
+use std::collections::HashMap;
+
+use easegress_macros::easegress_object;
+use easegress_sdk::*;
+
+#[easegress_object]
+struct AddRequestHeader {
+    max_length: usize,
+}
+
+#[easegress_object]
+impl Program for AddRequestHeader {
+    fn new(params: HashMap<String, String>) -> Self {
+        let max_length = params
+            .get("maxLength")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10);
+        Self { max_length }
+    }
+
+    fn run(&self) -> i32 {
+        let v = request::get_header("Foo".to_string());
+        if v.len() > self.max_length {
+            log(
+                LogLevel::Warning,
+                format!("The length of Foo is greater than {}", self.max_length),
+            );
+        }
+
+        if !v.is_empty() {
+            request::add_header("Wasm-Added".to_string(), v);
+        }
+
+        let cookies = request::get_all_cookie().len();
+        request::set_header("Wasm-Cookies".to_string(), cookies.to_string());
+
+        let body = request::get_body();
+        let body = format!(
+            "I have a new body now, the old one had {} bytes",
+            body.len()
+        );
+        request::set_body(body.into_bytes());
+        0
+    }
+}